@@ -0,0 +1,44 @@
+use criterion::{criterion_group, criterion_main, Criterion, black_box};
+use std::fs;
+use aoc::{DAYS, find_root_dir, input_path};
+use aoc::config::Config;
+use exrunner::Rng;
+
+// Benchmark the parse, part1 and part2 phases of every day separately.
+// Uses the real input (or the input set in aoc.toml) when present, otherwise the input generated by the solver.
+// Criterion stores the results under target/criterion/<day dir>/<phase>/, where aoc reads them
+// back to pick the slowest days for --slowest. Select days with: cargo bench -- day6
+fn bench_days(c: &mut Criterion) {
+    let rootdir = find_root_dir(None).ok();
+    let config = rootdir.as_ref().and_then(|r| Config::load(r).ok()).unwrap_or_default();
+    for d in DAYS {
+        let real_input = rootdir.as_ref().and_then(|r| fs::read_to_string(input_path(r, d, config.input(d))).ok());
+        let input = match real_input {
+            Some(i) => i,
+            None => {
+                eprintln!("{}: no input.txt, using generated input", d.dir);
+                d.solver.generate_any(&mut Rng::new(d.solver.day() as u64))
+            },
+        };
+        let parsed = match d.solver.parse_any(&input) {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("{}: skipped, cannot parse input: {e}", d.dir);
+                continue;
+            },
+        };
+        let mut group = c.benchmark_group(d.dir);
+        if let Some(n) = config.bench_iterations {
+            // criterion needs at least 10 samples
+            group.sample_size(n.max(10));
+        }
+        // the names are the phases aoc::bench reads back
+        group.bench_function("parse", |b| b.iter_with_large_drop(|| d.solver.parse_any(black_box(&input))));
+        group.bench_function("part1", |b| b.iter(|| d.solver.part1_any(black_box(parsed.as_ref()))));
+        group.bench_function("part2", |b| b.iter(|| d.solver.part2_any(black_box(parsed.as_ref()))));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_days);
+criterion_main!(benches);
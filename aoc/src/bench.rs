@@ -0,0 +1,58 @@
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::Day;
+
+// the phases benches/days.rs measures for every day, in the order of the table columns
+pub const PHASES: [&str; 3] = ["parse", "part1", "part2"];
+
+// the part of criterion's estimates.json that is used
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    // in nanoseconds
+    point_estimate: f64,
+}
+
+// where `cargo bench` leaves the criterion results: target/criterion in the workspace, unless
+// CARGO_TARGET_DIR says otherwise
+pub fn criterion_dir(rootdir: &Path) -> PathBuf {
+    env::var_os("CARGO_TARGET_DIR").map(PathBuf::from).unwrap_or_else(|| rootdir.join("target")).join("criterion")
+}
+
+// The mean time of every phase in the last benchmark of a day. None when the day was not
+// benchmarked, or not all of its phases.
+pub fn read_bench(criterion_dir: &Path, day: &Day) -> Option<[Duration; 3]> {
+    let mean = |phase: &str| -> Option<Duration> {
+        let path = criterion_dir.join(day.dir).join(phase).join("new").join("estimates.json");
+        let estimates: Estimates = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+        Some(Duration::from_secs_f64(estimates.mean.point_estimate.max(0.0) / 1e9))
+    };
+    Some([mean(PHASES[0])?, mean(PHASES[1])?, mean(PHASES[2])?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DAYS;
+
+    #[test]
+    fn test_read_bench() {
+        let day6 = &DAYS[5];
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(read_bench(tmp.path(), day6), None);
+        for (phase, ns) in PHASES.iter().zip([1500.0, 2e6, 3e9]) {
+            let dir = tmp.path().join(day6.dir).join(phase).join("new");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("estimates.json"), format!(r#"{{"mean":{{"confidence_interval":{{}},"point_estimate":{ns},"standard_error":1.0}},"median":{{}}}}"#)).unwrap();
+        }
+        assert_eq!(read_bench(tmp.path(), day6), Some([Duration::from_nanos(1500), Duration::from_millis(2), Duration::from_secs(3)]));
+        assert_eq!(read_bench(tmp.path(), &DAYS[6]), None);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Deserialize;
+use crate::Day;
+
+pub const CONFIG_FILE: &str = "aoc.toml";
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Raw,
+    Table,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Colour {
+    // colours when writing to a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+// settings for a single day, these take precedence over the general ones
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DayConfig {
+    pub input: Option<String>,
+    pub timeout: Option<u64>,
+}
+
+// the contents of aoc.toml in the root directory. Everything is optional, command line
+// flags override the settings here.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    // only run the puzzles of this year, when not naming them on the command line
+    pub year: Option<u16>,
+    // input file name, instead of input.txt
+    pub input: Option<String>,
+    // output format, instead of raw for single puzzles and a table for --all
+    pub format: Option<Format>,
+    // stop when a puzzle runs longer than this many seconds
+    pub timeout: Option<u64>,
+    // number of samples per benchmark
+    pub bench_iterations: Option<usize>,
+    // file with the session cookie, relative to the root directory
+    pub session_cookie: Option<PathBuf>,
+    // the site to download inputs from, and to check the session cookie against
+    pub url: Option<String>,
+    pub colour: Colour,
+    // settings per day, by puzzle number: [day.12]
+    pub day: HashMap<String, DayConfig>,
+}
+
+impl Config {
+    // read aoc.toml from the root directory. No file means the defaults.
+    pub fn load(rootdir: &Path) -> io::Result<Config> {
+        let fname = rootdir.join(CONFIG_FILE);
+        match fs::read_to_string(&fname) {
+            Ok(s) => Config::parse(&s).map_err(|e| io::Error::new(ErrorKind::InvalidData,
+                format!("{}: {e}", fname.to_string_lossy()))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    // the settings of a day, which can be given as [day.12] or [day.day12]
+    fn for_day(&self, day: &Day) -> Option<&DayConfig> {
+        let num = day.solver.day().to_string();
+        self.day.iter()
+            .find(|(k, _)| k.strip_prefix("day").unwrap_or(k) == num || *k == day.dir)
+            .map(|(_, c)| c)
+    }
+
+    // the input file name of a day
+    pub fn input(&self, day: &Day) -> &str {
+        self.for_day(day).and_then(|c| c.input.as_deref())
+            .or(self.input.as_deref())
+            .unwrap_or("input.txt")
+    }
+
+    pub fn timeout(&self, day: &Day) -> Option<Duration> {
+        self.for_day(day).and_then(|c| c.timeout).or(self.timeout).map(Duration::from_secs)
+    }
+
+    // the days of the configured year, or all of them
+    pub fn days(&self, days: &[Day]) -> Vec<Day> {
+        days.iter().filter(|d| self.year.is_none_or(|y| d.solver.year() == y)).cloned().collect()
+    }
+
+    pub fn url(&self) -> &str {
+        self.url.as_deref().unwrap_or("https://adventofcode.com").trim_end_matches('/')
+    }
+
+    // where to find the session cookie file
+    pub fn session_cookie(&self, rootdir: &Path) -> PathBuf {
+        rootdir.join(self.session_cookie.as_deref().unwrap_or(Path::new("session.cookie")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DAYS;
+
+    #[test]
+    fn test_config() {
+        let config = Config::parse(r#"
+input = "real.txt"
+format = "table"
+timeout = 10
+colour = "never"
+session-cookie = "secrets/cookie"
+
+[day.12]
+input = "small.txt"
+
+[day.day15]
+timeout = 60
+"#).unwrap();
+        assert_eq!(config.format, Some(Format::Table));
+        assert_eq!(config.colour, Colour::Never);
+        assert_eq!((config.input(&DAYS[11]), config.input(&DAYS[0])), ("small.txt", "real.txt"));
+        assert_eq!((config.timeout(&DAYS[14]), config.timeout(&DAYS[11])), (Some(Duration::from_secs(60)), Some(Duration::from_secs(10))));
+        assert_eq!(config.session_cookie(Path::new("/aoc")), PathBuf::from("/aoc/secrets/cookie"));
+        assert_eq!(Config::default().input(&DAYS[0]), "input.txt");
+        assert_eq!(Config::parse("url = \"http://localhost:8080/\"").unwrap().url(), "http://localhost:8080");
+        assert_eq!(config.days(DAYS).len(), DAYS.len());
+        assert!(Config::parse("year = 2015").unwrap().days(DAYS).is_empty());
+        assert!(Config::parse("inptu = \"x\"").is_err());
+    }
+}
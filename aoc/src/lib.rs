@@ -1,17 +1,34 @@
-use std::collections::{HashMap, hash_map::Entry};
-use std::io::{Read, BufReader, ErrorKind};
+use std::io::{BufReader, ErrorKind};
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs, env};
-use std::os::unix::fs::MetadataExt;
 use std::time::Duration;
-use std::process::exit;
-use reqwest;
-use clap::{Args, Parser};
-use comfy_table::Table;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use clap::{ArgAction, Args, Parser, Subcommand};
+use comfy_table::{Cell, Color, Table};
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
-use exrunner::{ExRunner, duration_format, ExCtx};
+use exrunner::{DynSolver, duration_format, ExCtx, Level, ParseError};
+
+pub mod bench;
+pub mod config;
+pub mod lint;
+pub mod log;
+pub mod play;
+pub mod root;
+pub mod select;
+pub mod session;
+pub mod viz;
+
+use config::{Colour, Config, Format};
+use lint::LintArgs;
+use log::LogArgs;
+use play::PlayArgs;
+pub use root::find_root_dir;
+use select::{Selector, SelectError};
+use session::LoginArgs;
+use viz::VizArgs;
 
 /// command line tool to run Advent of Code puzzles and display output and timings
 ///
@@ -33,8 +50,47 @@ pub struct CliArgs {
     #[command(flatten)]
     format: OutputFormat,
 
-    /// which puzzle(s) to run
-    pub puzzle: Vec<u32>,
+    /// Show debug output of the puzzles: -v for info, -vv for debug, -vvv for trace messages
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only show debug output for this puzzle, like "12" or "day12". Can be repeated.
+    #[arg(long, value_name = "PUZZLE")]
+    pub debug: Vec<String>,
+
+    /// which puzzle(s) to run: numbers like 12 or day12, ranges like 1-5, or part of a name like chiton
+    pub puzzle: Vec<Selector>,
+
+    /// Only run the puzzles that failed in their last run
+    #[arg(long)]
+    pub failed: bool,
+
+    /// Only run the puzzles with changes since a git revision
+    #[arg(long, value_name = "REV")]
+    pub changed: Option<String>,
+
+    /// Only run the n slowest puzzles, by their last benchmark or else their last run
+    #[arg(long, value_name = "N")]
+    pub slowest: Option<usize>,
+
+    /// root directory of the puzzles (default: AOC_ROOT, or the first directory with aoc.toml
+    /// or the workspace Cargo.toml above the current directory)
+    #[arg(long, global = true)]
+    pub root: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    Viz(VizArgs),
+    Play(PlayArgs),
+    Log(LogArgs),
+    Login(LoginArgs),
+    Lint(LintArgs),
+    /// Check the session cookie, and show who it belongs to
+    Whoami,
 }
 
 #[derive(Args, Debug)]
@@ -52,13 +108,30 @@ struct OutputFormat {
 #[derive(Clone)]
 pub struct Day {
     pub dir: &'static str,
-    // Need to specify the specific type of BufReader<File> here, because function
-    // pointers to generic functions do not exist.
-    pub solve: fn(BufReader<File>, &mut ExRunner),
+    pub solver: &'static dyn DynSolver,
 }
 
+// all puzzle days. Note that the puzzle number should be the first number in the directory name.
+pub const DAYS: &[Day] = &[
+    Day{ dir: "day1_sonar_sweep", solver: &day1_sonar_sweep::SonarSweep },
+    Day{ dir: "day2_dive", solver: &day2_dive::Dive },
+    Day{ dir: "day3_binary_diagnostic", solver: &day3_binary_diagnostic::BinaryDiagnostic },
+    Day{ dir: "day4_giant_squid", solver: &day4_giant_squid::GiantSquid },
+    Day{ dir: "day5_hydrothermal_venture", solver: &day5_hydrothermal_venture::HydrothermalVenture },
+    Day{ dir: "day6_lanternfish", solver: &day6_lanternfish::Lanternfish },
+    Day{ dir: "day7_treachery_of_whales", solver: &day7_treachery_of_whales::TreacheryOfWhales },
+    Day{ dir: "day8_seven_segment", solver: &day8_seven_segment::SevenSegment },
+    Day{ dir: "day9_smoke_basin", solver: &day9_smoke_basin::SmokeBasin },
+    Day{ dir: "day10_syntax_scoring", solver: &day10_syntax_scoring::SyntaxScoring },
+    Day{ dir: "day11_dumbo_octopus", solver: &day11_dumbo_octopus::DumboOctopus },
+    Day{ dir: "day12_passage_passing", solver: &day12_passage_passing::PassagePathing },
+    Day{ dir: "day13_transparent_origami", solver: &day13_transparent_origami::TransparentOrigami },
+    Day{ dir: "day14_extended_polymerization", solver: &day14_extended_polymerization::ExtendedPolymerization },
+    Day{ dir: "day15_chiton", solver: &day15_chiton::Chiton },
+];
+
 // returns the first number in a string
-fn first_number<'a>(input: &'a str) -> &'a str {
+pub(crate) fn first_number(input: &str) -> &str {
     let start_off = input.find(|c: char| c.is_ascii_digit());
     if start_off.is_none() {
         return "";
@@ -68,31 +141,34 @@ fn first_number<'a>(input: &'a str) -> &'a str {
     &input[start_off..start_off+end_off]
 }
 
-// convert list of puzzle numbers to Vec of Day structures.
-pub fn to_days(puzzle: &Vec<u32>, days: &[Day]) -> Vec<Day> {
-    // keep hash of puzzle number and index
-    let mut puzzle_pos: HashMap<u32, Option<usize>> = HashMap::new();
-    for (index, d) in days.iter().enumerate() {
-        let puzzlenum: u32 = first_number(d.dir).parse().expect(&format!("Cannot find puzzle number in {}", d.dir));
-        assert!(!puzzle_pos.contains_key(&puzzlenum), "Duplicate puzzle number");
-        puzzle_pos.insert(puzzlenum, Some(index));
+// the puzzles selected on the command line: the named ones (or with --all, or when only filtering,
+// all of them), narrowed down by --failed, --changed and --slowest. None when nothing was selected.
+pub fn select_days(rootdir: &Path, args: &CliArgs, config: &Config) -> Result<Option<Vec<Day>>, SelectError> {
+    let filtering = args.failed || args.changed.is_some() || args.slowest.is_some();
+    let mut days = if !args.puzzle.is_empty() {
+        select::select(DAYS, &args.puzzle)?
+    } else if args.all || filtering {
+        config.days(DAYS)
+    } else {
+        return Ok(None);
+    };
+    if args.failed {
+        days = select::failed(rootdir, days);
     }
-    let mut result: Vec<Day> = Vec::new();
-    for p in puzzle {
-        match puzzle_pos.get(p) {
-            None => { eprintln!("Puzzle number {p} does not exist"); exit(1); },
-            Some(Some(i)) => { result.push(days[*i].clone()); puzzle_pos.insert(*p, None); },
-            Some(None) => { eprintln!("Trying to run puzzle {p} twice?"); exit(1); },
-        };
+    if let Some(rev) = &args.changed {
+        days = select::changed(rootdir, days, rev)?;
+    }
+    if let Some(n) = args.slowest {
+        days = select::slowest(rootdir, days, n);
     }
-    result
+    Ok(Some(days))
 }
 
 // Convert current directory to Day ref, or error if not found.
 pub fn current_puzzle(days: &'static [Day]) -> std::io::Result<&'static [Day]> {
     let curdir = env::current_dir()?;
     let curdir_str = curdir.to_string_lossy() + "/";
-    for (index, d) in days.into_iter().enumerate() {
+    for (index, d) in days.iter().enumerate() {
         if curdir_str.contains(&format!("/{}/", d.dir)) {
             return Ok(&days[index..=index]);
         }
@@ -100,16 +176,22 @@ pub fn current_puzzle(days: &'static [Day]) -> std::io::Result<&'static [Day]> {
     Err(std::io::Error::new(ErrorKind::NotFound, "Current directory is not a puzzle"))
 }
 
+// path of the input file of a puzzle
+pub fn input_path(rootdir: &Path, day: &Day, inputfile: &str) -> PathBuf {
+    rootdir.join(day.dir).join("input").join(inputfile)
+}
+
 // run a list of puzzles
-pub fn run_puzzles(rootdir: PathBuf, args: &CliArgs, days: &[Day], year: u16) {
-    let defaultinput = String::from("input.txt");
-    let inputfile  = args.input.as_ref().unwrap_or(&defaultinput);
+pub fn run_puzzles(rootdir: PathBuf, args: &CliArgs, config: &Config, days: &[Day]) {
     // determine output format, raw or table
     let f_raw;
     let f_table;
     if args.format.raw || args.format.table {
         f_raw = args.format.raw;
         f_table = args.format.table;
+    } else if let Some(format) = config.format {
+        f_table = format == Format::Table;
+        f_raw = !f_table;
     } else if args.all {
         f_table = true;
         f_raw = false;
@@ -121,17 +203,21 @@ pub fn run_puzzles(rootdir: PathBuf, args: &CliArgs, days: &[Day], year: u16) {
     if f_table {
         table.load_preset(UTF8_FULL)
             .apply_modifier(UTF8_ROUND_CORNERS);
+        match config.colour {
+            Colour::Always => { table.enforce_styling(); },
+            Colour::Never => { table.force_no_tty(); },
+            Colour::Auto => (),
+        }
     }
     table.set_header(vec!["", "part1", "part2", "parse", "time1", "time2", "close"]);
     let mut total_time = Duration::from_secs(0);
     for (index, d) in days.iter().enumerate() {
-        let mut fname = rootdir.clone();
-        fname.push(d.dir);
-        fname.push("input");
-        fname.push(inputfile);
+        let inputfile = args.input.as_deref().unwrap_or(config.input(d));
+        let fname = input_path(&rootdir, d, inputfile);
         let meta = fs::metadata(&fname);
         match meta {
-            Err(e) if e.kind() == ErrorKind::NotFound && args.input.is_none() => download_input(&rootdir, &d.dir, &fname, year),
+            // only the real input can be downloaded
+            Err(e) if e.kind() == ErrorKind::NotFound && inputfile == "input.txt" => download_input(&rootdir, config, d.solver, &fname),
             Err(e) => panic!("Error fetching {}: {e}", fname.to_string_lossy()),
             Ok(m) if !m.is_file() => panic!("{} is not a file, but a {:?}", fname.to_string_lossy(), m),
             _ => (),
@@ -141,25 +227,14 @@ pub fn run_puzzles(rootdir: PathBuf, args: &CliArgs, days: &[Day], year: u16) {
             eprintln!("Error: cannot open file {} for exercise {}: {e}", fname.to_string_lossy(), d.dir);
             continue;
         }
-        let mut ct = ExCtx::new(d.solve, BufReader::new(fh.unwrap()));
-        if f_raw {
-            ct.with_stderr();
-        }
-        let er = ct.do_run(d.dir.to_string());
-        total_time += er.totaltime().unwrap_or(Duration::from_secs(0));
-        if f_raw {
-            if index > 0 {
-                println!("---");
-            }
-            er.print_raw();
-        }
+        let day = DayRun { rootdir: rootdir.clone(), day: d.clone(), inputfile: inputfile.to_string(), fname, level: trace_level(args, d), index, f_raw };
+        let finished = match config.timeout(d) {
+            Some(timeout) => day.run_with_timeout(fh.unwrap(), timeout),
+            None => day.run(fh.unwrap()),
+        };
+        let (row, time) = day.report(finished);
+        total_time += time;
         if f_table {
-            let mut row = vec![d.dir.to_string()];
-            let mut answers: Vec<String> = er.answ().into_iter().map(|x| x.unwrap_or(String::from(""))).collect();
-            row.append(&mut answers);
-            let mut times: Vec<String> = [er.parsetime(), er.time1(), er.time2(), er.cleanuptime()].iter()
-                .map(|x| if let Some(d) = x { duration_format(d) } else { String::from("") }).collect();
-            row.append(&mut times);
             table.add_row(row);
         }
     }
@@ -174,14 +249,180 @@ pub fn run_puzzles(rootdir: PathBuf, args: &CliArgs, days: &[Day], year: u16) {
     }
 }
 
+// a puzzle to run, with where its input and output go
+#[derive(Clone)]
+struct DayRun {
+    rootdir: PathBuf,
+    day: Day,
+    inputfile: String,
+    fname: PathBuf,
+    level: Option<Level>,
+    // the position in the list of puzzles to run
+    index: usize,
+    f_raw: bool,
+}
+
+// what running a puzzle gave, to be reported once it is done
+struct Finished {
+    row: Vec<Cell>,
+    time: Duration,
+    last_run: log::LastRun,
+    log: String,
+    // the debug messages, shown in raw mode
+    debug: Vec<String>,
+    // the answers, or the parse error with the offending part of the input file
+    output: String,
+    failed_parse: bool,
+}
+
+impl DayRun {
+    // run the puzzle, without reporting anything yet
+    fn run(&self, input: File) -> Finished {
+        let d = &self.day;
+        let mut ct = ExCtx::from_solver(d.solver, BufReader::new(input));
+        if let Some(level) = self.level {
+            ct.with_trace(level);
+        }
+        let er = ct.do_run(d.dir.to_string());
+        // show parse errors with the offending part of the input file
+        let diagnostic = er.error().map(|e| {
+            let e = e.clone().in_file(self.fname.to_string_lossy());
+            match fs::read_to_string(&self.fname) {
+                Ok(input) => render_parse_error(&e, &input),
+                Err(_) => format!("error: {e}"),
+            }
+        });
+        let mut row = vec![Cell::new(d.dir)];
+        let mut answers: Vec<Cell> = er.answ().into_iter().map(|x| Cell::new(x.unwrap_or_default()).fg(Color::Green)).collect();
+        if let Some(e) = er.error() {
+            answers[0] = Cell::new(format!("parse error: {e}")).fg(Color::Red);
+        }
+        row.append(&mut answers);
+        let mut times: Vec<Cell> = [er.parsetime(), er.time1(), er.time2(), er.cleanuptime()].iter()
+            .map(|x| Cell::new(if let Some(d) = x { duration_format(d) } else { String::from("") })).collect();
+        row.append(&mut times);
+        Finished {
+            row,
+            time: er.totaltime().unwrap_or_default(),
+            last_run: log::LastRun::new(&er, &self.inputfile),
+            log: log::format_log(&er, &self.inputfile),
+            debug: er.log().iter().map(|rec| format!("{} {rec}", d.dir)).collect(),
+            failed_parse: diagnostic.is_some(),
+            output: diagnostic.unwrap_or_else(|| er.raw()),
+        }
+    }
+
+    // Save and show the result of a run. Returns the row of the table, and how long it took.
+    fn report(&self, finished: Finished) -> (Vec<Cell>, Duration) {
+        let dir = self.day.dir;
+        if let Err(e) = log::write_run(&self.rootdir, &self.day, &finished.last_run) {
+            eprintln!("Cannot save the result of {dir}: {e}");
+        }
+        // always replace the log, so it never belongs to an older run
+        if let Err(e) = log::write_log(&self.rootdir, &self.day, &finished.log) {
+            eprintln!("Cannot write debug log of {dir}: {e}");
+        }
+        if self.f_raw {
+            for line in &finished.debug {
+                eprintln!("{line}");
+            }
+            if self.index > 0 {
+                println!("---");
+            }
+            if finished.failed_parse {
+                println!("{dir} failed to parse input:");
+            }
+            print!("{}", finished.output);
+        } else if finished.failed_parse {
+            eprint!("{dir}: {}", finished.output);
+        }
+        (finished.row, finished.time)
+    }
+
+    // Run the puzzle in a thread of its own, and give up on it when it runs too long. There is no
+    // way to interrupt the puzzle itself, so it is left running until the program ends, and the
+    // run is recorded as failed.
+    fn run_with_timeout(&self, input: File, timeout: Duration) -> Finished {
+        let (tx, rx) = mpsc::channel();
+        let day = self.clone();
+        let worker = thread::spawn(move || {
+            // the receiver is gone when the puzzle timed out
+            let _ = tx.send(day.run(input));
+        });
+        let msg = format!("did not finish within {}", duration_format(&timeout));
+        match rx.recv_timeout(timeout) {
+            Ok(finished) => finished,
+            // the puzzle panicked, pass that on
+            Err(RecvTimeoutError::Disconnected) => match worker.join() {
+                Err(panic) => std::panic::resume_unwind(panic),
+                Ok(()) => unreachable!("The puzzle sends its result before finishing"),
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                if !self.f_raw {
+                    eprintln!("{} {msg}", self.day.dir);
+                }
+                Finished {
+                    row: vec![Cell::new(self.day.dir), Cell::new(format!("timed out: {msg}")).fg(Color::Red)],
+                    time: timeout,
+                    last_run: log::LastRun { input: self.inputfile.clone(), failed: true, seconds: timeout.as_secs_f64() },
+                    log: format!("# {} with {}, {msg}\n", self.day.dir, self.inputfile),
+                    debug: Vec::new(),
+                    output: format!("{} {msg}\n", self.day.dir),
+                    failed_parse: false,
+                }
+            },
+        }
+    }
+}
+
+// the level of debug output for a puzzle. --debug without -v means debug level, for the puzzles
+// it names only.
+fn trace_level(args: &CliArgs, day: &Day) -> Option<Level> {
+    if args.debug.is_empty() {
+        return Level::from_verbosity(args.verbose);
+    }
+    let selected = args.debug.iter().any(|p| {
+        p == day.dir || p.strip_prefix("day").unwrap_or(p).parse() == Ok(day.solver.day())
+    });
+    if selected {
+        Level::from_verbosity(args.verbose).or(Some(Level::Debug))
+    } else {
+        None
+    }
+}
+
+// render a parse error like a compiler error: the message, the location, and the input line
+// with the offending text underlined.
+pub fn render_parse_error(e: &ParseError, input: &str) -> String {
+    let mut out = format!("error: {}\n", e.msg());
+    let location: Vec<String> = [e.file().map(|f| f.to_string()), e.line().map(|l| l.to_string()), e.col().map(|c| c.to_string())]
+        .into_iter().flatten().collect();
+    if !location.is_empty() {
+        out += &format!(" --> {}\n", location.join(":"));
+    }
+    // lines and columns count from 1, there is no source to show for line 0
+    let src = e.line().and_then(|l| input.lines().nth(l.checked_sub(1)?));
+    if let (Some(line), Some(src)) = (e.line(), src) {
+        let num = line.to_string();
+        let pad = " ".repeat(num.len());
+        out += &format!("{pad} |\n{num} | {src}\n");
+        if let Some(col) = e.col() {
+            // keep tabs, so the caret lines up with the text above it
+            let indent: String = src.chars().take(col.saturating_sub(1)).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let len = e.text().map(|t| t.chars().count()).unwrap_or(0).max(1);
+            out += &format!("{pad} | {indent}{}\n", "^".repeat(len));
+        }
+    }
+    out
+}
+
 // download input to puzzle
-fn download_input(rootdir: &PathBuf, dirname: &str, target: &PathBuf, year: u16) {
-    let session_cookie = match get_session_cookie(rootdir) {
+fn download_input(rootdir: &Path, config: &Config, solver: &dyn DynSolver, target: &Path) {
+    let session_cookie = match session::session_cookie(rootdir, config) {
         Err(e) => panic!("No input file, and no session cookie found: {e}"),
         Ok(s) => format!("session={s}"),
     };
-    let daynum = first_number(dirname);
-    let url = format!("https://adventofcode.com/{year}/day/{daynum}/input");
+    let url = format!("{}/{}/day/{}/input", config.url(), solver.year(), solver.day());
     let client = reqwest::blocking::Client::new();
     let res = client.get(&url)
         .header(reqwest::header::COOKIE, session_cookie)
@@ -206,73 +447,6 @@ fn download_input(rootdir: &PathBuf, dirname: &str, target: &PathBuf, year: u16)
     resp.copy_to(&mut fh).expect("Error reading from URL writing to example input");
 }
 
-fn get_session_cookie(rootdir: &PathBuf) -> std::io::Result<String> {
-    match env::var("SESSION_COOKIE") {
-        Ok(s) => return Ok(s),
-        _ => (),
-    };
-    let mut cookiefile = rootdir.clone();
-    cookiefile.push("session.cookie");
-    let mut fh = File::open(cookiefile)?;
-    let mut contents = String::new();
-    fh.read_to_string(&mut contents)?;
-    Ok(contents.trim().to_string())
-}
-
-// libc-specific: get access to uid
-#[link(name="c")]
-extern "C" {
-    fn geteuid() -> u32;
-}
-
-// Find the root directory of the puzzles by looking up from the current dir, or from the directory of the binary
-pub fn find_root_dir(target: &str) -> std::io::Result<PathBuf> {
-    // only look at directories that are owned by the current user, so get euid.
-    let uid: u32;
-    unsafe { uid = geteuid(); }
-    // maintain a hash of directories that we looked at
-    let mut seen: HashMap<PathBuf, ()> = HashMap::new();
-    let root_dir =
-        find_in_ancestors(env::current_dir()?, target, uid, &mut seen).or_else(|_|
-            // search again, from program
-            find_in_ancestors(PathBuf::from(env::args().next().unwrap()).canonicalize()?, target, uid, &mut seen))?;
-    return Ok(root_dir);
-}
-
-// find a subdirectory somewhere in the current dir or one of the directories above, only checking directories owned by the given uid.
-fn find_in_ancestors(startdir: PathBuf, target: &str, uid: u32, seen: &mut HashMap<PathBuf, ()>) -> std::io::Result<PathBuf> {
-    // try_find would make this a bit cleaner, but that's only in nightly at the moment.
-    for d in startdir.ancestors() {
-        // verify d is a directory and is owned by the right user
-        let attr = fs::metadata(d)?;
-        // if it's not a directory, skip it.
-        if !attr.is_dir() {
-            continue;
-        }
-        // if it has the wrong uid, stop immediately
-        if attr.uid() != uid {
-            return Err(std::io::Error::new(ErrorKind::PermissionDenied, "Cannot find target directory"));
-        }
-        // if it's in the seen map, we have seen this dir already and we can abort with a "not found"
-        let e = seen.entry(d.to_path_buf());
-        if let Entry::Occupied(_) = e {
-            return Err(std::io::Error::new(ErrorKind::NotFound, "Cannot find target directory"));
-        }
-        // try to access the target dir and see if it exists
-        let mut targetdir = d.to_path_buf();
-        targetdir.push(target);
-        let t_attr = fs::metadata(&targetdir);
-        if t_attr.is_ok() && t_attr.as_ref().unwrap().is_dir() && t_attr.as_ref().unwrap().uid() == uid {
-            // target dir exists, so parent is root dir we want.
-            return Ok(d.to_path_buf());
-        }
-        // mark that we've searched this path, and continue up the tree
-        e.or_insert(());
-    }
-    // if we get here, we didn't find it
-    Err(std::io::Error::new(ErrorKind::NotFound, "Searched all the way to the top, nothing found"))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,4 +457,54 @@ mod tests {
         assert_eq!(first_number("123"), "123");
         assert_eq!(first_number("yolo"), "");
     }
+
+    #[test]
+    fn test_render_parse_error() {
+        let e = ParseError::new("Input should be numeric").at_line(2).at_col(5).with_text("x7").in_file("input.txt");
+        assert_eq!(render_parse_error(&e, "1 2 3\n1 2 x7 4\n"),
+"error: Input should be numeric
+ --> input.txt:2:5
+  |
+2 | 1 2 x7 4
+  |     ^^
+");
+        assert_eq!(render_parse_error(&ParseError::new("Input should contain boards"), ""), "error: Input should contain boards\n");
+        // without a file the line and column are still shown
+        assert_eq!(render_parse_error(&ParseError::new("Bad").at_line(1).at_col(0).with_text("x"), "x\n"),
+"error: Bad
+ --> 1:0
+  |
+1 | x
+  | ^
+");
+        assert_eq!(render_parse_error(&ParseError::new("Bad").at_line(0).at_col(3), "x\n"), "error: Bad\n --> 0:3\n");
+    }
+
+    #[test]
+    fn test_trace_level() {
+        let day12 = &DAYS[11];
+        let args = CliArgs::parse_from(["aoc", "-vv"]);
+        assert_eq!(trace_level(&args, day12), Some(Level::Debug));
+        let args = CliArgs::parse_from(["aoc", "--debug", "day12"]);
+        assert_eq!(trace_level(&args, day12), Some(Level::Debug));
+        assert_eq!(trace_level(&args, &DAYS[10]), None);
+        let args = CliArgs::parse_from(["aoc", "-vvv", "--debug", "12"]);
+        assert_eq!(trace_level(&args, day12), Some(Level::Trace));
+        assert_eq!(trace_level(&CliArgs::parse_from(["aoc"]), day12), None);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let day12 = &DAYS[11];
+        let fname = tmp.path().join("example.txt");
+        fs::write(&fname, day12.solver.example()).unwrap();
+        let day = || DayRun { rootdir: tmp.path().to_path_buf(), day: day12.clone(), inputfile: "example.txt".to_string(), fname: fname.clone(), level: None, index: 0, f_raw: false };
+        let (row, _) = day().report(day().run_with_timeout(File::open(&fname).unwrap(), Duration::ZERO));
+        assert_eq!(row[1].content(), "timed out: did not finish within 0ns");
+        assert!(log::read_run(tmp.path(), day12).unwrap().failed);
+        let (row, _) = day().report(day().run_with_timeout(File::open(&fname).unwrap(), Duration::from_secs(60)));
+        assert_eq!(row[1].content(), "226");
+        assert!(!log::read_run(tmp.path(), day12).unwrap().failed);
+    }
 }
@@ -0,0 +1,41 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use clap::Args;
+use day10_syntax_scoring::Brackets;
+use crate::render_parse_error;
+
+/// Check the brackets in a file, with the syntax checker of day 10
+#[derive(Args, Debug)]
+pub struct LintArgs {
+    /// the file to check
+    pub file: PathBuf,
+
+    /// the bracket pairs to check, opener followed by closer
+    #[arg(short, long, default_value = "()[]{}<>")]
+    pub pairs: String,
+}
+
+// the problems in the file, rendered like compiler errors. Empty when the brackets are fine.
+pub fn run_lint(args: &LintArgs) -> io::Result<Vec<String>> {
+    let brackets = Brackets::parse(&args.pairs).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+    let text = fs::read_to_string(&args.file)?;
+    let file = args.file.to_string_lossy();
+    Ok(brackets.lint(&text).into_iter().map(|e| render_parse_error(&e.in_file(file.clone()), &text)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("code.txt");
+        fs::write(&file, "ok ()\n[x)\n").unwrap();
+        let problems = run_lint(&LintArgs { file: file.clone(), pairs: "()[]".to_string() }).unwrap();
+        let name = file.to_string_lossy();
+        assert_eq!(problems, [format!("error: Unexpected ), expected ] to close [ from line 2, column 1; replace it with ]\n --> {name}:2:3\n  |\n2 | [x)\n  |   ^\n")]);
+        assert!(run_lint(&LintArgs { file, pairs: "(".to_string() }).is_err());
+    }
+}
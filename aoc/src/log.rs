@@ -0,0 +1,121 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use exrunner::{duration_format, ExRunner};
+use crate::Day;
+
+/// Show the debug output of the last run of a puzzle
+#[derive(Args, Debug)]
+pub struct LogArgs {
+    /// which puzzle to show the log of
+    pub puzzle: u32,
+}
+
+// where the debug output of the last run of a puzzle is kept
+pub fn log_path(rootdir: &Path, day: &Day) -> PathBuf {
+    rootdir.join(day.dir).join("output").join("debug.log")
+}
+
+// how the last run of a puzzle went, for selecting puzzles with --failed and --slowest
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LastRun {
+    pub input: String,
+    // could not parse the input, or did not give both answers
+    pub failed: bool,
+    pub seconds: f64,
+}
+
+impl LastRun {
+    pub fn new(er: &ExRunner, inputfile: &str) -> LastRun {
+        LastRun {
+            input: inputfile.to_string(),
+            failed: er.error().is_some() || er.answ().iter().any(|a| a.is_none()),
+            seconds: er.totaltime().unwrap_or_default().as_secs_f64(),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.seconds.max(0.0))
+    }
+}
+
+fn run_path(rootdir: &Path, day: &Day) -> PathBuf {
+    rootdir.join(day.dir).join("output").join("last-run.toml")
+}
+
+// remember how a run went, replacing the previous one
+pub fn write_run(rootdir: &Path, day: &Day, run: &LastRun) -> io::Result<()> {
+    let target = run_path(rootdir, day);
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(target, toml::to_string(run).map_err(io::Error::other)?)
+}
+
+// the last run of a puzzle, if there was one that can be read
+pub fn read_run(rootdir: &Path, day: &Day) -> Option<LastRun> {
+    toml::from_str(&fs::read_to_string(run_path(rootdir, day)).ok()?).ok()
+}
+
+// the log of a run: a line about the run, followed by the debug messages
+pub fn format_log(er: &ExRunner, inputfile: &str) -> String {
+    let total = er.totaltime().map(|t| duration_format(&t)).unwrap_or_default();
+    let mut log = format!("# {} with {inputfile}, took {total}\n", er.name());
+    if er.log().is_empty() {
+        log += "# no debug messages, run with -v or --debug to record them\n";
+    }
+    for rec in er.log() {
+        log += &format!("{rec}\n");
+    }
+    log
+}
+
+// keep the debug output of a run, replacing the log of the previous run
+pub fn write_log(rootdir: &Path, day: &Day, log: &str) -> io::Result<()> {
+    let target = log_path(rootdir, day);
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(target, log)
+}
+
+// the log of the last run, with a hint when there is none
+pub fn read_log(rootdir: &Path, day: &Day) -> io::Result<String> {
+    fs::read_to_string(log_path(rootdir, day)).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => io::Error::new(ErrorKind::NotFound,
+            format!("no debug log yet, run puzzle {} with -v or --debug first", day.solver.day())),
+        _ => e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+    use exrunner::{ExCtx, Level};
+    use crate::DAYS;
+
+    #[test]
+    fn test_format_log() {
+        let day12 = &DAYS[11];
+        let mut ct = ExCtx::from_solver(day12.solver, BufReader::new(day12.solver.example().as_bytes()));
+        ct.with_trace(Level::Info);
+        let er = ct.do_run(day12.dir.to_string());
+        let log = format_log(&er, "example");
+        let lines: Vec<&str> = log.lines().collect();
+        assert!(lines[0].starts_with("# day12_passage_passing with example, took "));
+        assert_eq!(lines[1..], ["[parse info] 10 caves, 18 passages"]);
+        // without tracing there is still a log, saying so
+        let er = ExCtx::from_solver(day12.solver, BufReader::new(day12.solver.example().as_bytes())).do_run(day12.dir.to_string());
+        assert_eq!(format_log(&er, "example").lines().nth(1), Some("# no debug messages, run with -v or --debug to record them"));
+        let run = LastRun::new(&er, "example");
+        assert!(!run.failed);
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(read_run(tmp.path(), day12), None);
+        write_run(tmp.path(), day12, &run).unwrap();
+        assert_eq!(read_run(tmp.path(), day12), Some(run));
+    }
+}
@@ -2,53 +2,116 @@ use clap::{Parser, CommandFactory};
 use std::process::exit;
 use std::io::ErrorKind;
 use aoc::*;
-
-const YEAR: u16 = 2021;
-
-// all puzzle days. Note that the puzzle number should be the first number in the directory name.
-const DAYS: &'static [Day] = &[
-    Day{ dir: "day1_sonar_sweep", solve: day1_sonar_sweep::solve },
-    Day{ dir: "day2_dive", solve: day2_dive::solve },
-    Day{ dir: "day3_binary_diagnostic", solve: day3_binary_diagnostic::solve },
-    Day{ dir: "day4_giant_squid", solve: day4_giant_squid::solve },
-    Day{ dir: "day5_hydrothermal_venture", solve: day5_hydrothermal_venture::solve },
-    Day{ dir: "day6_lanternfish", solve: day6_lanternfish::solve },
-    Day{ dir: "day7_treachery_of_whales", solve: day7_treachery_of_whales::solve },
-    Day{ dir: "day8_seven_segment", solve: day8_seven_segment::solve },
-    Day{ dir: "day9_smoke_basin", solve: day9_smoke_basin::solve },
-    Day{ dir: "day10_syntax_scoring", solve: day10_syntax_scoring::solve },
-    Day{ dir: "day11_dumbo_octopus", solve: day11_dumbo_octopus::solve },
-    Day{ dir: "day12_passage_passing", solve: day12_passage_passing::solve },
-    Day{ dir: "day13_transparent_origami", solve: day13_transparent_origami::solve },
-    Day{ dir: "day14_extended_polymerization", solve: day14_extended_polymerization::solve },
-    Day{ dir: "day15_chiton", solve: day15_chiton::solve },
-];
+use aoc::config::Config;
 
 fn main() {
     let args = CliArgs::parse();
-    // reject "--all" and explicit puzzle numbers
+    // reject "--all" and explicit puzzles
     if args.all && !args.puzzle.is_empty() {
         let mut cmd = CliArgs::command();
         cmd.error(clap::error::ErrorKind::ArgumentConflict,
-            "Cannot use --all and explicit puzzle numbers.")
+            "Cannot use --all and explicit puzzles.")
             .exit();
     }
-    let rootdir = find_root_dir(&DAYS[0].dir);
-    if let Err(e) = rootdir {
-        eprintln!("Cannot find path to exercises: {:?}", e);
-        exit(2);
+    // linting works on any file, outside of the puzzles
+    if let Some(Command::Lint(la)) = &args.command {
+        match lint::run_lint(la) {
+            Ok(problems) if problems.is_empty() => println!("No problems in {}", la.file.to_string_lossy()),
+            Ok(problems) => {
+                problems.iter().for_each(|p| eprintln!("{p}"));
+                exit(1);
+            },
+            Err(e) => {
+                eprintln!("Cannot lint {}: {e}", la.file.to_string_lossy());
+                exit(2);
+            },
+        }
+        return;
+    }
+    let rootdir = match find_root_dir(args.root.as_deref()) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{e}");
+            exit(2);
+        },
+    };
+    let config = match Config::load(&rootdir) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Cannot read configuration: {e}");
+            exit(2);
+        },
+    };
+    if let Some(Command::Viz(va)) = &args.command {
+        let day = &one_day(va.puzzle);
+        match viz::run_viz(&rootdir, &config, va, day) {
+            Ok(files) => files.iter().for_each(|f| println!("Wrote {}", f.to_string_lossy())),
+            Err(e) => {
+                eprintln!("Cannot visualise {}: {e}", day.dir);
+                exit(1);
+            },
+        }
+        return;
+    }
+    if let Some(Command::Play(pa)) = &args.command {
+        let day = &one_day(pa.puzzle);
+        if let Err(e) = play::run_play(&rootdir, &config, pa, day) {
+            eprintln!("Cannot play {}: {e}", day.dir);
+            exit(1);
+        }
+        return;
+    }
+    if let Some(Command::Log(la)) = &args.command {
+        let day = &one_day(la.puzzle);
+        match log::read_log(&rootdir, day) {
+            Ok(log) => print!("{log}"),
+            Err(e) => {
+                eprintln!("Cannot show log of {}: {e}", day.dir);
+                exit(1);
+            },
+        }
+        return;
+    }
+    if let Some(Command::Login(la)) = &args.command {
+        if let Err(e) = session::login(&rootdir, &config, la) {
+            eprintln!("Cannot store session cookie: {e}");
+            exit(1);
+        }
+        return;
+    }
+    if let Some(Command::Whoami) = &args.command {
+        match session::session_cookie(&rootdir, &config).and_then(|c| session::whoami(config.url(), &c)) {
+            Ok(name) => println!("Logged in as {name}"),
+            Err(e) => {
+                eprintln!("{e}");
+                exit(1);
+            },
+        }
+        return;
     }
-    let rootdir = rootdir.unwrap();
     // which puzzles to run
-    if args.all {
-        run_puzzles(rootdir, &args, &DAYS, YEAR);
-    } else if !args.puzzle.is_empty() {
-        run_puzzles(rootdir, &args, &to_days(&args.puzzle, &DAYS), YEAR);
+    let selected = match select_days(&rootdir, &args, &config) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{e}");
+            exit(1);
+        },
+    };
+    if let Some(days) = selected {
+        if days.is_empty() {
+            println!("No puzzles selected");
+        } else {
+            run_puzzles(rootdir, &args, &config, &days);
+        }
     } else {
-        let puzzle = current_puzzle(&DAYS);
+        let puzzle = current_puzzle(DAYS);
         match puzzle {
-            Ok(d) => run_puzzles(rootdir, &args, d, YEAR),
-            Err(e) if e.kind() == ErrorKind::NotFound => run_puzzles(rootdir, &args, &DAYS[DAYS.len()-1..], YEAR),
+            Ok(d) => run_puzzles(rootdir, &args, &config, d),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                // the latest puzzle
+                let days = config.days(DAYS);
+                run_puzzles(rootdir, &args, &config, &days[days.len().saturating_sub(1)..]);
+            },
             Err(e) => {
                 eprintln!("Error searching for puzzle from current dir: {e}");
                 exit(1);
@@ -57,3 +120,13 @@ fn main() {
     }
 }
 
+// a single puzzle by number, for the subcommands
+fn one_day(puzzle: u32) -> Day {
+    match select::day(DAYS, puzzle) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("{e}");
+            exit(1);
+        },
+    }
+}
@@ -0,0 +1,170 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+use clap::Args;
+use crossterm::{cursor, execute, queue, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use exrunner::{Rgb, Screen};
+use crate::{Config, Day, input_path, render_parse_error};
+
+/// Play a simulation step by step in the terminal
+#[derive(Args, Debug)]
+pub struct PlayArgs {
+    /// which puzzle to play
+    pub puzzle: u32,
+
+    /// input file name (default: input.txt)
+    #[arg(short, long)]
+    pub input: Option<String>,
+
+    /// delay between steps, in milliseconds
+    #[arg(short, long, default_value_t = 200)]
+    pub delay: u64,
+}
+
+const MIN_DELAY: Duration = Duration::from_millis(10);
+const MAX_DELAY: Duration = Duration::from_secs(5);
+
+// the playback state, changed by key presses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Playback {
+    pub paused: bool,
+    pub delay: Duration,
+    // show one more step while paused
+    pub step_once: bool,
+    pub quit: bool,
+}
+
+impl Playback {
+    pub fn new(delay: Duration) -> Playback {
+        Playback { paused: false, delay: delay.clamp(MIN_DELAY, MAX_DELAY), step_once: false, quit: false }
+    }
+
+    pub fn key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(' ') => self.paused = !self.paused,
+            KeyCode::Char('n') | KeyCode::Char('.') | KeyCode::Right => {
+                self.paused = true;
+                self.step_once = true;
+            },
+            KeyCode::Char('+') | KeyCode::Up => self.delay = (self.delay / 2).max(MIN_DELAY),
+            KeyCode::Char('-') | KeyCode::Down => self.delay = (self.delay * 2).min(MAX_DELAY),
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            _ => (),
+        }
+    }
+}
+
+// puts the terminal in raw mode on the alternate screen, and restores it when dropped
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<RawTerminal> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        // nothing sensible to do when restoring fails
+        let _ = execute!(io::stdout(), ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// run the steps of a puzzle in the terminal until the user quits
+pub fn run_play(rootdir: &Path, config: &Config, args: &PlayArgs, day: &Day) -> io::Result<()> {
+    let fname = input_path(rootdir, day, args.input.as_deref().unwrap_or(config.input(day)));
+    let input = fs::read_to_string(&fname)?;
+    let parsed = day.solver.parse_any(&input).map_err(|e| {
+        let e = e.in_file(fname.to_string_lossy());
+        io::Error::new(io::ErrorKind::InvalidData, render_parse_error(&e, &input))
+    })?;
+    let mut steps = day.solver.play_any(parsed.as_ref());
+    let mut current = steps.next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, format!("{} has no playback", day.dir)))?;
+    let _term = RawTerminal::enter()?;
+    let mut out = io::stdout().lock();
+    let mut pb = Playback::new(Duration::from_millis(args.delay));
+    let mut step = 1;
+    let mut finished = false;
+    loop {
+        draw(&mut out, &current, step, &pb, finished)?;
+        // wait for a key press, or until it is time for the next step
+        let advance = if (pb.paused || finished) || event::poll(pb.delay)? {
+            if let Event::Key(k) = event::read()? {
+                if k.kind == KeyEventKind::Press {
+                    pb.key(k.code);
+                }
+            }
+            std::mem::take(&mut pb.step_once)
+        } else {
+            true
+        };
+        if pb.quit {
+            return Ok(());
+        }
+        if advance && !finished {
+            match steps.next() {
+                Some(s) => {
+                    current = s;
+                    step += 1;
+                },
+                None => finished = true,
+            }
+        }
+    }
+}
+
+fn colour(c: Rgb) -> Color {
+    Color::Rgb { r: c.0, g: c.1, b: c.2 }
+}
+
+fn draw(out: &mut impl Write, screen: &Screen, step: usize, pb: &Playback, finished: bool) -> io::Result<()> {
+    queue!(out, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0), ResetColor, Print(&screen.caption))?;
+    for (y, row) in screen.cells.rows().enumerate() {
+        queue!(out, cursor::MoveTo(0, y as u16 + 2))?;
+        let mut fg = None;
+        for cell in row {
+            // only switch colours when needed, that keeps the output small
+            if fg != Some(cell.fg) {
+                queue!(out, SetForegroundColor(colour(cell.fg)))?;
+                fg = Some(cell.fg);
+            }
+            queue!(out, Print(cell.ch))?;
+        }
+    }
+    let state = if finished { " (finished)" } else if pb.paused { " (paused)" } else { "" };
+    let status = format!("step {step}{state}, {}ms per step", pb.delay.as_millis());
+    let help = "space: pause  n: single step  +/-: speed  q: quit";
+    let y = screen.cells.height() as u16 + 3;
+    queue!(out, ResetColor, cursor::MoveTo(0, y), Print(status), cursor::MoveTo(0, y + 1), Print(help))?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback_keys() {
+        let mut pb = Playback::new(Duration::from_millis(100));
+        pb.key(KeyCode::Char(' '));
+        assert!(pb.paused);
+        pb.key(KeyCode::Char('+'));
+        assert_eq!(pb.delay, Duration::from_millis(50));
+        pb.key(KeyCode::Char('-'));
+        pb.key(KeyCode::Char('-'));
+        assert_eq!(pb.delay, Duration::from_millis(200));
+        pb.key(KeyCode::Char(' '));
+        pb.key(KeyCode::Char('n'));
+        assert!(pb.paused && pb.step_once);
+        assert!(!pb.quit);
+        pb.key(KeyCode::Char('q'));
+        assert!(pb.quit);
+    }
+}
@@ -0,0 +1,134 @@
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use crate::config::CONFIG_FILE;
+
+// the root directory could not be found. Lists every directory that was looked at, and why it
+// was not the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootError {
+    pub checked: Vec<(PathBuf, String)>,
+}
+
+impl Display for RootError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cannot find the root directory of the puzzles, it should contain {CONFIG_FILE} or the workspace Cargo.toml. \
+            Use --root or AOC_ROOT to set it.")?;
+        for (dir, reason) in &self.checked {
+            write!(f, "\n  {}: {reason}", dir.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RootError {}
+
+// Find the root directory of the puzzles. That is the --root argument, or else AOC_ROOT, or else
+// the first directory with a marker file above the current directory or above the program.
+pub fn find_root_dir(root_arg: Option<&Path>) -> Result<PathBuf, RootError> {
+    let explicit = match root_arg {
+        Some(r) => Some((r.to_path_buf(), "--root")),
+        None => env::var_os("AOC_ROOT").map(|r| (PathBuf::from(r), "AOC_ROOT")),
+    };
+    let mut starts = Vec::new();
+    if let Ok(d) = env::current_dir() {
+        starts.push(d);
+    }
+    if let Some(d) = env::current_exe().ok().and_then(|exe| exe.parent().map(|p| p.to_path_buf())) {
+        starts.push(d);
+    }
+    search_root(explicit, &starts)
+}
+
+// the search itself: an explicitly given directory wins, otherwise walk up from every start directory
+pub fn search_root(explicit: Option<(PathBuf, &str)>, starts: &[PathBuf]) -> Result<PathBuf, RootError> {
+    if let Some((dir, source)) = explicit {
+        return match fs::metadata(&dir) {
+            Ok(m) if m.is_dir() => Ok(dir),
+            Ok(_) => Err(RootError { checked: vec![(dir, format!("given by {source}, but not a directory"))] }),
+            Err(e) => Err(RootError { checked: vec![(dir, format!("given by {source}, but {e}"))] }),
+        };
+    }
+    let mut checked: Vec<(PathBuf, String)> = Vec::new();
+    for start in starts {
+        for dir in start.ancestors() {
+            // the directories above have been checked already as well
+            if checked.iter().any(|(c, _)| c == dir) {
+                break;
+            }
+            match root_marker(dir) {
+                Ok(()) => return Ok(dir.to_path_buf()),
+                Err(reason) => checked.push((dir.to_path_buf(), reason)),
+            }
+        }
+    }
+    Err(RootError { checked })
+}
+
+// is dir the root: does it have an aoc.toml or a workspace Cargo.toml. The error says why not.
+fn root_marker(dir: &Path) -> Result<(), String> {
+    if dir.join(CONFIG_FILE).is_file() {
+        return Ok(());
+    }
+    match fs::read_to_string(dir.join("Cargo.toml")) {
+        Ok(s) if s.lines().any(|l| l.trim() == "[workspace]") => Ok(()),
+        Ok(_) => Err(format!("no {CONFIG_FILE}, and Cargo.toml is not a workspace")),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(format!("no {CONFIG_FILE} or Cargo.toml")),
+        Err(e) => Err(format!("cannot read Cargo.toml: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // a root with a workspace, and a puzzle directory with its own Cargo.toml
+    fn tree() -> TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("ws/day1/src")).unwrap();
+        fs::write(tmp.path().join("ws/Cargo.toml"), "[workspace]\nmembers = [\"day1\"]\n").unwrap();
+        fs::write(tmp.path().join("ws/day1/Cargo.toml"), "[package]\nname = \"day1\"\n").unwrap();
+        tmp
+    }
+
+    #[test]
+    fn test_workspace_root() {
+        let tmp = tree();
+        let ws = tmp.path().join("ws");
+        assert_eq!(search_root(None, &[ws.join("day1/src")]), Ok(ws.clone()));
+        // an aoc.toml is found first
+        fs::write(ws.join("day1/aoc.toml"), "").unwrap();
+        assert_eq!(search_root(None, &[ws.join("day1/src")]), Ok(ws.join("day1")));
+    }
+
+    #[test]
+    fn test_explicit_root() {
+        let tmp = tree();
+        let other = tmp.path().join("other");
+        assert_eq!(search_root(Some((tmp.path().to_path_buf(), "--root")), &[]), Ok(tmp.path().to_path_buf()));
+        let e = search_root(Some((other.clone(), "AOC_ROOT")), &[tmp.path().join("ws")]).unwrap_err();
+        assert_eq!(e.checked.len(), 1);
+        assert!(e.checked[0].1.starts_with("given by AOC_ROOT, but "));
+    }
+
+    #[test]
+    fn test_no_root() {
+        let tmp = tree();
+        fs::remove_file(tmp.path().join("ws/Cargo.toml")).unwrap();
+        let start = tmp.path().join("ws/day1/src");
+        let e = search_root(None, &[start.clone(), tmp.path().join("ws")]).unwrap_err();
+        assert_eq!(e.checked[..3], [
+            (start, format!("no {CONFIG_FILE} or Cargo.toml")),
+            (tmp.path().join("ws/day1"), format!("no {CONFIG_FILE}, and Cargo.toml is not a workspace")),
+            (tmp.path().join("ws"), format!("no {CONFIG_FILE} or Cargo.toml")),
+        ]);
+        // every directory is checked once, even with more than one start
+        let dirs: Vec<&PathBuf> = e.checked.iter().map(|(d, _)| d).collect();
+        assert_eq!(dirs.iter().filter(|d| **d == &tmp.path().join("ws")).count(), 1);
+        assert!(e.to_string().contains("ws/day1: no aoc.toml, and Cargo.toml is not a workspace"));
+    }
+}
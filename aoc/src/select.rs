@@ -0,0 +1,166 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::{Day, bench, first_number, log};
+
+// a way to name puzzles on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    // 12 or day12
+    Number(u32),
+    // 1-5, both ends included
+    Range(u32, u32),
+    // part of the directory name, like chiton
+    Name(String),
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Selector, String> {
+        let number = |t: &str| t.strip_prefix("day").unwrap_or(t).parse::<u32>();
+        if let Ok(n) = number(s) {
+            return Ok(Selector::Number(n));
+        }
+        if let Some((Ok(from), Ok(to))) = s.split_once('-').map(|(a, b)| (number(a), number(b))) {
+            return if from <= to {
+                Ok(Selector::Range(from, to))
+            } else {
+                Err(format!("Range {s} is the wrong way around"))
+            };
+        }
+        if s.is_empty() {
+            return Err("Empty puzzle name".to_string());
+        }
+        Ok(Selector::Name(s.to_lowercase()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectError {
+    NoSuchPuzzle(u32),
+    NoMatch(String),
+    Git(String),
+}
+
+impl Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelectError::NoSuchPuzzle(n) => write!(f, "Puzzle number {n} does not exist"),
+            SelectError::NoMatch(name) => write!(f, "No puzzle matches '{name}'"),
+            SelectError::Git(msg) => write!(f, "Cannot find changed puzzles: {msg}"),
+        }
+    }
+}
+
+impl Error for SelectError {}
+
+// the puzzle number of a day, the first number in its directory name
+pub fn number(day: &Day) -> u32 {
+    first_number(day.dir).parse().unwrap_or_else(|_| panic!("Cannot find puzzle number in {}", day.dir))
+}
+
+// a single puzzle by number
+pub fn day(days: &[Day], puzzle: u32) -> Result<Day, SelectError> {
+    days.iter().find(|d| number(d) == puzzle).cloned().ok_or(SelectError::NoSuchPuzzle(puzzle))
+}
+
+// the days matching any of the selectors, in the order they are selected. Days selected
+// more than once are only run once.
+pub fn select(days: &[Day], selectors: &[Selector]) -> Result<Vec<Day>, SelectError> {
+    let mut result: Vec<Day> = Vec::new();
+    for sel in selectors {
+        let found: Vec<&Day> = match sel {
+            Selector::Number(n) => vec![days.iter().find(|d| number(d) == *n).ok_or(SelectError::NoSuchPuzzle(*n))?],
+            Selector::Range(from, to) => days.iter().filter(|d| (*from..=*to).contains(&number(d))).collect(),
+            Selector::Name(name) => days.iter().filter(|d| d.dir.to_lowercase().contains(name.as_str())).collect(),
+        };
+        if found.is_empty() {
+            let name = match sel {
+                Selector::Name(name) => name.clone(),
+                Selector::Range(from, to) => format!("{from}-{to}"),
+                Selector::Number(n) => n.to_string(),
+            };
+            return Err(SelectError::NoMatch(name));
+        }
+        for d in found {
+            if !result.iter().any(|r| r.dir == d.dir) {
+                result.push(d.clone());
+            }
+        }
+    }
+    Ok(result)
+}
+
+// the days that failed in their last run: the input could not be parsed, or there were no answers
+pub fn failed(rootdir: &Path, days: Vec<Day>) -> Vec<Day> {
+    days.into_iter().filter(|d| log::read_run(rootdir, d).is_some_and(|r| r.failed)).collect()
+}
+
+// The n slowest days, slowest first. A day is timed by its last `cargo bench` when there is one,
+// that is more precise than its last run. Days that did not run yet are left out.
+pub fn slowest(rootdir: &Path, days: Vec<Day>, n: usize) -> Vec<Day> {
+    let criterion_dir = bench::criterion_dir(rootdir);
+    let mut timed: Vec<(Duration, Day)> = days.into_iter()
+        .filter_map(|d| bench::read_bench(&criterion_dir, &d).map(|b| b.iter().sum())
+            .or_else(|| log::read_run(rootdir, &d).map(|r| r.duration()))
+            .map(|t| (t, d)))
+        .collect();
+    timed.sort_by_key(|t| std::cmp::Reverse(t.0));
+    timed.into_iter().take(n).map(|(_, d)| d).collect()
+}
+
+// the days with files that differ from a git revision, including new files that are not
+// ignored. Only changes to the crate of a day count, not to its inputs.
+pub fn changed(rootdir: &Path, days: Vec<Day>, since: &str) -> Result<Vec<Day>, SelectError> {
+    let git = |args: &[&str]| -> Result<String, SelectError> {
+        let out = Command::new("git").arg("-C").arg(rootdir).args(args).output()
+            .map_err(|e| SelectError::Git(format!("cannot run git: {e}")))?;
+        if !out.status.success() {
+            return Err(SelectError::Git(String::from_utf8_lossy(&out.stderr).trim().to_string()));
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    };
+    // paths relative to the root, which need not be the top of the git repository
+    let files = git(&["diff", "--name-only", "--relative", since, "--"])?
+        + &git(&["ls-files", "--others", "--exclude-standard"])?;
+    Ok(changed_days(days, &files))
+}
+
+fn changed_days(days: Vec<Day>, files: &str) -> Vec<Day> {
+    days.into_iter().filter(|d| files.lines().any(|f| {
+        let mut parts = f.split('/');
+        parts.next() == Some(d.dir) && parts.next() != Some("input")
+    })).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DAYS;
+
+    fn dirs(days: &[Day]) -> Vec<&str> {
+        days.iter().map(|d| d.dir).collect()
+    }
+
+    #[test]
+    fn test_selectors() {
+        let sel: Vec<Selector> = ["3-5", "day12", "CHITON", "4"].iter().map(|s| s.parse().unwrap()).collect();
+        assert_eq!(sel[..2], [Selector::Range(3, 5), Selector::Number(12)]);
+        assert_eq!(dirs(&select(DAYS, &sel).unwrap()), ["day3_binary_diagnostic", "day4_giant_squid",
+            "day5_hydrothermal_venture", "day12_passage_passing", "day15_chiton"]);
+        assert!("5-3".parse::<Selector>().is_err());
+        assert_eq!(select(DAYS, &[Selector::Number(42)]).err(), Some(SelectError::NoSuchPuzzle(42)));
+        assert_eq!(select(DAYS, &["squids".parse().unwrap()]).err().map(|e| e.to_string()), Some("No puzzle matches 'squids'".to_string()));
+        assert_eq!(day(DAYS, 6).unwrap().dir, "day6_lanternfish");
+    }
+
+    #[test]
+    fn test_changed_days() {
+        let files = "day1_sonar_sweep/src/lib.rs\nexrunner/src/lib.rs\nday11_dumbo_octopus/Cargo.toml\nday6_lanternfish/input/input.txt\n";
+        assert_eq!(dirs(&changed_days(DAYS.to_vec(), files)), ["day1_sonar_sweep", "day11_dumbo_octopus"]);
+    }
+}
@@ -0,0 +1,211 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use argon2::Argon2;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use chacha20poly1305::aead::Aead;
+use clap::Args;
+use crate::config::Config;
+
+/// Store the session cookie used to download inputs
+#[derive(Args, Debug)]
+pub struct LoginArgs {
+    /// encrypt the cookie with a passphrase
+    #[arg(short, long)]
+    pub encrypt: bool,
+}
+
+// encrypted cookie files start with this, followed by the salt, the nonce and the encrypted cookie
+const MAGIC: &[u8] = b"aoc-session-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+// check that a cookie looks like a session cookie: a long hex string. Accepts a pasted
+// "session=..." as well. Returns the bare cookie.
+pub fn validate_cookie(cookie: &str) -> io::Result<String> {
+    let cookie = cookie.trim();
+    let cookie = cookie.strip_prefix("session=").unwrap_or(cookie);
+    if cookie.len() < 64 || !cookie.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid("Session cookie should be a hex string of at least 64 characters"));
+    }
+    Ok(cookie.to_string())
+}
+
+// a warning when others than the owner can read the file
+pub fn permission_warning(path: &Path) -> io::Result<Option<String>> {
+    let mode = fs::metadata(path)?.permissions().mode();
+    Ok((mode & 0o077 != 0).then(|| format!("Warning: {} can be read by others, fix with: chmod 600 {0}", path.to_string_lossy())))
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> io::Result<ChaCha20Poly1305> {
+    let mut key = [0u8; 32];
+    Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| invalid(format!("Cannot derive key: {e}")))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+fn encrypt(cookie: &str, passphrase: &str) -> io::Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut salt).and_then(|_| getrandom::getrandom(&mut nonce)).map_err(io::Error::other)?;
+    let encrypted = cipher(passphrase, &salt)?.encrypt(Nonce::from_slice(&nonce), cookie.as_bytes())
+        .map_err(|_| invalid("Cannot encrypt cookie"))?;
+    Ok([MAGIC, &salt, &nonce, &encrypted].concat())
+}
+
+fn decrypt(data: &[u8], passphrase: &str) -> io::Result<String> {
+    let data = &data[MAGIC.len()..];
+    if data.len() < SALT_LEN + NONCE_LEN {
+        return Err(invalid("Encrypted cookie file is too short"));
+    }
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, encrypted) = rest.split_at(NONCE_LEN);
+    let cookie = cipher(passphrase, salt)?.decrypt(Nonce::from_slice(nonce), encrypted)
+        .map_err(|_| invalid("Cannot decrypt cookie, wrong passphrase?"))?;
+    String::from_utf8(cookie).map_err(|_| invalid("Decrypted cookie is not text"))
+}
+
+// write the cookie readable for the owner only, encrypted when there is a passphrase
+pub fn store_cookie(path: &Path, cookie: &str, passphrase: Option<&str>) -> io::Result<()> {
+    let contents = match passphrase {
+        Some(p) => encrypt(cookie, p)?,
+        None => format!("{cookie}\n").into_bytes(),
+    };
+    let mut fh = OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    // mode only applies to new files, so fix existing ones too
+    fh.set_permissions(fs::Permissions::from_mode(0o600))?;
+    fh.write_all(&contents)
+}
+
+// read a cookie file, asking for the passphrase when it is encrypted
+pub fn read_cookie(path: &Path, passphrase: impl FnOnce() -> io::Result<String>) -> io::Result<String> {
+    let data = fs::read(path)?;
+    if let Some(warning) = permission_warning(path)? {
+        eprintln!("{warning}");
+    }
+    let cookie = if data.starts_with(MAGIC) {
+        decrypt(&data, &passphrase()?)?
+    } else {
+        String::from_utf8(data).map_err(|_| invalid("Session cookie file is not text"))?
+    };
+    validate_cookie(&cookie)
+}
+
+// the passphrase from AOC_PASSPHRASE, or else asked for on the terminal
+pub fn passphrase() -> io::Result<String> {
+    match env::var("AOC_PASSPHRASE") {
+        Ok(p) => Ok(p),
+        Err(_) => rpassword::prompt_password("Passphrase: "),
+    }
+}
+
+// the session cookie from SESSION_COOKIE, or the cookie file
+pub fn session_cookie(rootdir: &Path, config: &Config) -> io::Result<String> {
+    if let Ok(s) = env::var("SESSION_COOKIE") {
+        return validate_cookie(&s);
+    }
+    read_cookie(&config.session_cookie(rootdir), passphrase)
+}
+
+// ask for the cookie and store it
+pub fn login(rootdir: &Path, config: &Config, args: &LoginArgs) -> io::Result<()> {
+    let cookie = validate_cookie(&rpassword::prompt_password("Session cookie: ")?)?;
+    let passphrase = if args.encrypt {
+        let p = passphrase()?;
+        if env::var("AOC_PASSPHRASE").is_err() && rpassword::prompt_password("Passphrase again: ")? != p {
+            return Err(invalid("Passphrases do not match"));
+        }
+        Some(p)
+    } else {
+        None
+    };
+    let target = config.session_cookie(rootdir);
+    store_cookie(&target, &cookie, passphrase.as_deref())?;
+    println!("Stored session cookie in {}", target.to_string_lossy());
+    Ok(())
+}
+
+// the user name the site shows for the cookie, or an error when the cookie is not accepted
+pub fn whoami(url: &str, cookie: &str) -> io::Result<String> {
+    // a logged out visitor gets redirected, so do not follow redirects
+    let client = reqwest::blocking::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build().map_err(io::Error::other)?;
+    let resp = client.get(format!("{url}/settings"))
+        .header(reqwest::header::COOKIE, format!("session={cookie}"))
+        .send().map_err(io::Error::other)?;
+    if !resp.status().is_success() {
+        return Err(io::Error::new(ErrorKind::PermissionDenied, format!("Session cookie not accepted: {}", resp.status())));
+    }
+    let page = resp.text().map_err(io::Error::other)?;
+    // the name is shown as <div class="user">name <span class="star-count">...
+    page.split_once("<div class=\"user\">")
+        .and_then(|(_, rest)| rest.split_once('<'))
+        .map(|(name, _)| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| io::Error::new(ErrorKind::PermissionDenied, "Session cookie not accepted: not logged in"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const COOKIE: &str = "53616c7465645f5f0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn test_cookie_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.cookie");
+        fs::write(&path, "x").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(permission_warning(&path).unwrap().is_some());
+        store_cookie(&path, COOKIE, None).unwrap();
+        assert_eq!(permission_warning(&path).unwrap(), None);
+        assert_eq!(read_cookie(&path, || panic!("not encrypted")).unwrap(), COOKIE);
+        store_cookie(&path, COOKIE, Some("secret")).unwrap();
+        assert!(!fs::read(&path).unwrap().windows(COOKIE.len()).any(|w| w == COOKIE.as_bytes()));
+        assert_eq!(read_cookie(&path, || Ok("secret".to_string())).unwrap(), COOKIE);
+        assert!(read_cookie(&path, || Ok("wrong".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_validate_cookie() {
+        assert_eq!(validate_cookie(&format!("session={COOKIE}\n")).unwrap(), COOKIE);
+        assert!(validate_cookie("session=abc").is_err());
+        assert!(validate_cookie(&COOKIE.replace('a', "g")).is_err());
+    }
+
+    // answer a single request like the settings page of the site, returns the request headers
+    fn stub_server(status: &'static str, body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request: Vec<String> = BufReader::new(&stream).lines().map_while(Result::ok).take_while(|l| !l.is_empty()).collect();
+            write!(stream, "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len()).unwrap();
+            request.join("\n")
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_whoami() {
+        let (url, server) = stub_server("200 OK", "<div class=\"user\">jp <span class=\"star-count\">50*</span></div>");
+        assert_eq!(whoami(&url, COOKIE).unwrap(), "jp");
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /settings "));
+        assert!(request.contains(&format!("session={COOKIE}")));
+        let (url, server) = stub_server("302 Found", "");
+        assert_eq!(whoami(&url, COOKIE).unwrap_err().kind(), ErrorKind::PermissionDenied);
+        server.join().unwrap();
+    }
+}
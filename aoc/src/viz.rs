@@ -0,0 +1,144 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use clap::{Args, ValueEnum};
+use exrunner::{ExCtx, Frame, Rgb, viz::to_ppm};
+use crate::{Config, Day, input_path, render_parse_error};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VizFormat {
+    /// one PPM image per frame
+    Ppm,
+    /// one PNG image per frame
+    Png,
+    /// a single animated GIF
+    Gif,
+}
+
+/// Write frames of the solver's state as images
+#[derive(Args, Debug)]
+pub struct VizArgs {
+    /// which puzzle to visualise
+    pub puzzle: u32,
+
+    /// input file name (default: input.txt)
+    #[arg(short, long)]
+    pub input: Option<String>,
+
+    /// image format
+    #[arg(short, long, value_enum, default_value_t = VizFormat::Gif)]
+    pub format: VizFormat,
+
+    /// output directory (default: the viz directory of the puzzle)
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+
+    /// size in pixels of every cell
+    #[arg(short, long, default_value_t = 4)]
+    pub scale: usize,
+
+    /// maximum number of frames
+    #[arg(long, default_value_t = 500)]
+    pub max_frames: usize,
+
+    /// delay between GIF frames, in milliseconds
+    #[arg(long, default_value_t = 100)]
+    pub delay: u16,
+}
+
+// run a puzzle with visualisation on, and write the frames. Returns the files written.
+pub fn run_viz(rootdir: &Path, config: &Config, args: &VizArgs, day: &Day) -> io::Result<Vec<PathBuf>> {
+    let fname = input_path(rootdir, day, args.input.as_deref().unwrap_or(config.input(day)));
+    let mut ct = ExCtx::from_solver(day.solver, BufReader::new(File::open(&fname)?));
+    ct.with_viz(args.max_frames);
+    let er = ct.do_run(day.dir.to_string());
+    if let Some(e) = er.error() {
+        let e = e.clone().in_file(fname.to_string_lossy());
+        let msg = render_parse_error(&e, &fs::read_to_string(&fname)?);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    let frames: Vec<Frame> = er.frames().iter().map(|f| scale(f, args.scale)).collect();
+    if frames.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} has no visualisation", day.dir)));
+    }
+    let outdir = args.out.clone().unwrap_or_else(|| rootdir.join(day.dir).join("viz"));
+    fs::create_dir_all(&outdir)?;
+    match args.format {
+        VizFormat::Gif => {
+            let target = outdir.join(format!("{}.gif", day.dir));
+            write_gif(&target, &frames, args.delay)?;
+            Ok(vec![target])
+        },
+        format => frames.iter().enumerate().map(|(i, f)| {
+            let ext = if format == VizFormat::Png { "png" } else { "ppm" };
+            let target = outdir.join(format!("frame{:04}.{ext}", i + 1));
+            if format == VizFormat::Png {
+                write_png(&target, f)?;
+            } else {
+                fs::write(&target, to_ppm(f))?;
+            }
+            Ok(target)
+        }).collect(),
+    }
+}
+
+// blow up every cell to a square of n by n pixels
+fn scale(frame: &Frame, n: usize) -> Frame {
+    let n = n.max(1);
+    let mut big = Frame::new(frame.width() * n, frame.height() * n, Rgb::BLACK);
+    for (x, y) in big.positions().collect::<Vec<_>>() {
+        big[(x, y)] = frame[(x / n, y / n)];
+    }
+    big
+}
+
+fn rgb_bytes(frame: &Frame) -> Vec<u8> {
+    frame.values().flat_map(|Rgb(r, g, b)| [*r, *g, *b]).collect()
+}
+
+fn write_png(target: &Path, frame: &Frame) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(target)?), frame.width() as u32, frame.height() as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&rgb_bytes(frame)).map_err(io::Error::other)
+}
+
+// write all frames as one animated GIF. Frames can differ in size, all of them are drawn in the
+// upper left corner of a canvas that fits the largest.
+fn write_gif(target: &Path, frames: &[Frame], delay_ms: u16) -> io::Result<()> {
+    let width = frames.iter().map(|f| f.width()).max().unwrap_or(0);
+    let height = frames.iter().map(|f| f.height()).max().unwrap_or(0);
+    let too_big = || io::Error::new(io::ErrorKind::InvalidInput, "Frames are too big for a GIF, use a smaller scale");
+    let (w16, h16) = (u16::try_from(width).map_err(|_| too_big())?, u16::try_from(height).map_err(|_| too_big())?);
+    let mut fh = BufWriter::new(File::create(target)?);
+    let mut encoder = gif::Encoder::new(&mut fh, w16, h16, &[]).map_err(io::Error::other)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+    for f in frames {
+        let mut canvas = Frame::new(width, height, Rgb::BLACK);
+        for ((x, y), c) in f.iter() {
+            canvas[(x, y)] = *c;
+        }
+        let mut gf = gif::Frame::from_rgb_speed(w16, h16, &rgb_bytes(&canvas), 10);
+        // gif delays are in units of 10ms
+        gf.delay = delay_ms / 10;
+        encoder.write_frame(&gf).map_err(io::Error::other)?;
+    }
+    drop(encoder);
+    fh.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale() {
+        let mut f = Frame::new(2, 1, Rgb::BLACK);
+        f[(1, 0)] = Rgb::WHITE;
+        let big = scale(&f, 2);
+        assert_eq!((big.width(), big.height()), (4, 2));
+        assert_eq!(big[(3, 1)], Rgb::WHITE);
+        assert_eq!(big[(1, 1)], Rgb::BLACK);
+    }
+}
@@ -1,111 +1,338 @@
-use exrunner::ExRunner;
-use std::io::BufRead;
+use exrunner::{Solver, ExRunner, ParseError, Text};
 use std::collections::HashMap;
-use std::str;
+use std::io::BufRead;
 
-fn init_braces() -> HashMap<u8, u8> {
-    let mut res = HashMap::new();
-    res.insert(b'(', b')');
-    res.insert(b'[', b']');
-    res.insert(b'{', b'}');
-    res.insert(b'<', b'>');
-    res
+// the pairs of brackets the checker knows, as opener and closer. Other characters are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Brackets {
+    pairs: Vec<(char, char)>,
 }
 
-fn init_scores1() -> HashMap<u8, i32> {
-    let mut res = HashMap::new();
-    res.insert(b')', 3);
-    res.insert(b']', 57);
-    res.insert(b'}', 1197);
-    res.insert(b'>', 25137);
-    res
+impl Default for Brackets {
+    // the four pairs of the puzzle
+    fn default() -> Brackets {
+        Brackets { pairs: vec![('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')] }
+    }
 }
 
-fn init_scores2() -> HashMap<u8, i32> {
-    let mut res = HashMap::new();
-    res.insert(b')', 1);
-    res.insert(b']', 2);
-    res.insert(b'}', 3);
-    res.insert(b'>', 4);
-    res
+impl Brackets {
+    // pairs written one after the other, like "()[]". Every character can only be used once.
+    pub fn parse(pairs: &str) -> Result<Brackets, String> {
+        let chars: Vec<char> = pairs.chars().collect();
+        if chars.is_empty() || !chars.len().is_multiple_of(2) {
+            return Err(format!("Brackets should be pairs of opener and closer, not '{pairs}'"));
+        }
+        if let Some(c) = chars.iter().enumerate().find(|(i, c)| chars[..*i].contains(c)).map(|(_, c)| c) {
+            return Err(format!("Bracket {c} is used more than once"));
+        }
+        Ok(Brackets { pairs: chars.chunks(2).map(|p| (p[0], p[1])).collect() })
+    }
+
+    pub fn closer(&self, opener: char) -> Option<char> {
+        self.pairs.iter().find(|p| p.0 == opener).map(|p| p.1)
+    }
+
+    fn is_closer(&self, c: char) -> bool {
+        self.pairs.iter().any(|p| p.1 == c)
+    }
+
+    // the closers that complete the open brackets, innermost first
+    pub fn completion(&self, stack: &[Open]) -> String {
+        stack.iter().rev().filter_map(|o| self.closer(o.opener)).collect()
+    }
+
+    // Scan text from byte offset `from` with the brackets in stack still open. Returns the offset
+    // and character of the first closer that does not match, with the stack as it is there.
+    fn scan(&self, text: &str, from: usize, stack: &mut Vec<Open>) -> Option<(usize, char)> {
+        for (i, c) in text[from..].char_indices() {
+            let offset = from + i;
+            if self.closer(c).is_some() {
+                stack.push(Open { offset, opener: c });
+            } else if self.is_closer(c) {
+                if stack.last().and_then(|o| self.closer(o.opener)) != Some(c) {
+                    return Some((offset, c));
+                }
+                stack.pop();
+            }
+        }
+        None
+    }
+
+    pub fn check(&self, text: &str) -> Check {
+        let mut stack = Vec::new();
+        match self.scan(text, 0, &mut stack) {
+            Some((offset, found)) => Check::Corrupted { offset, found, expected: stack.last().copied(), stack },
+            None if stack.is_empty() => Check::Ok,
+            None => Check::Incomplete { stack },
+        }
+    }
+
+    fn opener(&self, closer: char) -> Option<char> {
+        self.pairs.iter().find(|p| p.1 == closer).map(|p| p.0)
+    }
+
+    // The fewest edits that make the text balanced, sorted by offset. When there are several
+    // ways, open brackets are closed at the end of the text, and otherwise matched as far apart
+    // as possible.
+    pub fn repair(&self, text: &str) -> Vec<Edit> {
+        self.fixes(text).into_iter().map(|f| f.edit).collect()
+    }
+
+    // the fewest edits that make the text balanced, with the brackets the new closers close
+    fn fixes(&self, text: &str) -> Vec<Fix> {
+        // matching pairs next to each other never need an edit, so only the rest is searched. The
+        // rest are kept as Open, closers included.
+        let mut rest: Vec<Open> = Vec::new();
+        for (offset, c) in text.char_indices() {
+            if self.is_closer(c) && rest.last().and_then(|o| self.closer(o.opener)) == Some(c) {
+                rest.pop();
+            } else if self.is_closer(c) || self.closer(c).is_some() {
+                rest.push(Open { offset, opener: c });
+            }
+        }
+        let repair = Repair::new(self, &rest);
+        let mut fixes = Vec::new();
+        repair.fixes(0, rest.len(), text.len(), &mut fixes);
+        // insertions go before the character at their offset
+        fixes.sort_by_key(|f| (f.edit.offset(), !matches!(f.edit, Edit::Insert(..))));
+        fixes
+    }
+
+    // the problems in a text with their fixes, as errors with the line and column they are at
+    pub fn lint(&self, text: &str) -> Vec<ParseError> {
+        let position = |offset: usize| {
+            let before = &text[..offset];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+        };
+        let at = |offset: usize, msg: String| {
+            let (line, col) = position(offset);
+            ParseError::new(msg).at_line(line).at_col(col).with_text(text[offset..].chars().take(1).collect::<String>())
+        };
+        self.fixes(text).into_iter().map(|fix| match (fix.edit, fix.closes) {
+            (Edit::Insert(offset, c), Some(open)) if offset == text.len() =>
+                at(open.offset, format!("{} is never closed, insert {c} at the end", open.opener)),
+            (edit, closes) => {
+                let found = text[edit.offset()..].chars().next().unwrap_or_default();
+                let expected = match closes {
+                    Some(o) => {
+                        let (line, col) = position(o.offset);
+                        format!(", expected {} to close {} from line {line}, column {col}", self.closer(o.opener).unwrap(), o.opener)
+                    },
+                    None => String::new(),
+                };
+                let change = match edit {
+                    Edit::Replace(_, c) => format!("replace it with {c}"),
+                    Edit::Delete(_) => "remove it".to_string(),
+                    Edit::Insert(_, c) => format!("insert {c} before it"),
+                };
+                at(edit.offset(), format!("Unexpected {found}{expected}; {change}"))
+            },
+        }).collect()
+    }
 }
 
-#[derive(Debug)]
-enum BraceParsed {
-    Ok,
-    FailChar(u8),
-    Incomplete(String),
+// an edit of a repair, with the open bracket it closes when it adds a closer
+#[derive(Debug, Clone, Copy)]
+struct Fix {
+    edit: Edit,
+    closes: Option<Open>,
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let braces = init_braces();
-    let scores1 = init_scores1();
-    let scores2 = init_scores2();
-    let mut total_score = 0;
-    let mut completion_scores = Vec::new();
-    for l in input.lines() {
-        let nav = l.expect("Error reading input");
-        let br = brace_matching(&nav, &braces);
-        er.debugln(&format!("Line = {}, matching brace = {:?}", nav, br));
-        match br {
-            BraceParsed::Ok => er.debugln(&("Somehow a complete line: ".to_string() + &nav)),
-            BraceParsed::FailChar(failbr) => {
-                if let Some(sc) = scores1.get(&failbr) {
-                    total_score += *sc;
-                } else {
-                    er.debugln(&format!("Warning: non-matching closing brace {} found", failbr as char));
+// how the brackets of a part are made balanced, by what happens to the first of them
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    // match it with the bracket at an index, replacing brackets when needed
+    Pair(usize),
+    // close it at the end of the part
+    Close,
+    Delete,
+}
+
+// Repair finds the fewest edits for a list of brackets from the fewest edits for each part of
+// the list. That takes time cubic in the number of brackets.
+struct Repair<'a> {
+    brackets: &'a Brackets,
+    rest: &'a [Open],
+    // the number of edits and the first step for the brackets i..j, at i * (len + 1) + j
+    best: Vec<(usize, Step)>,
+}
+
+impl<'a> Repair<'a> {
+    fn new(brackets: &'a Brackets, rest: &'a [Open]) -> Repair<'a> {
+        let len = rest.len();
+        let mut repair = Repair { brackets, rest, best: vec![(0, Step::Delete); (len + 1) * (len + 1)] };
+        for size in 1..=len {
+            for (i, first) in rest[..=len - size].iter().enumerate() {
+                let j = i + size;
+                let step = if brackets.closer(first.opener).is_some() { Step::Close } else { Step::Delete };
+                let mut best = (1 + repair.edits(i + 1, j), step);
+                for k in (i + 1..j).rev() {
+                    if let Some(edits) = repair.pair_edits(i, k) {
+                        let total = edits + repair.edits(i + 1, k) + repair.edits(k + 1, j);
+                        if total < best.0 {
+                            best = (total, Step::Pair(k));
+                        }
+                    }
+                }
+                repair.best[i * (len + 1) + j] = best;
+            }
+        }
+        repair
+    }
+
+    fn edits(&self, i: usize, j: usize) -> usize {
+        self.best[i * (self.rest.len() + 1) + j].0
+    }
+
+    // the replacements needed to match bracket i with bracket k, None when that takes two
+    fn pair_edits(&self, i: usize, k: usize) -> Option<usize> {
+        let (first, second) = (self.rest[i].opener, self.rest[k].opener);
+        match (self.brackets.closer(first), self.brackets.closer(second)) {
+            (Some(closer), None) => Some((closer != second) as usize),
+            (Some(_), Some(_)) | (None, None) => Some(1),
+            (None, Some(_)) => None,
+        }
+    }
+
+    // add the edits for the brackets i..j, where end is the offset in the text the part ends at
+    fn fixes(&self, i: usize, j: usize, end: usize, fixes: &mut Vec<Fix>) {
+        if i == j {
+            return;
+        }
+        let first = self.rest[i];
+        match self.best[i * (self.rest.len() + 1) + j].1 {
+            Step::Delete => {
+                fixes.push(Fix { edit: Edit::Delete(first.offset), closes: None });
+                self.fixes(i + 1, j, end, fixes);
+            },
+            Step::Close => {
+                // inner brackets are closed first
+                self.fixes(i + 1, j, end, fixes);
+                let closer = self.brackets.closer(first.opener).expect("Only openers are closed");
+                fixes.push(Fix { edit: Edit::Insert(end, closer), closes: Some(first) });
+            },
+            Step::Pair(k) => {
+                let second = self.rest[k];
+                let open = match self.brackets.closer(first.opener) {
+                    Some(_) => first,
+                    None => {
+                        let opener = self.brackets.opener(second.opener).expect("A closer pairs with a closer");
+                        fixes.push(Fix { edit: Edit::Replace(first.offset, opener), closes: None });
+                        Open { offset: first.offset, opener }
+                    },
+                };
+                let closer = self.brackets.closer(open.opener).expect("Pairs start with an opener");
+                if second.opener != closer {
+                    fixes.push(Fix { edit: Edit::Replace(second.offset, closer), closes: Some(open) });
                 }
+                self.fixes(i + 1, k, second.offset, fixes);
+                self.fixes(k + 1, j, end, fixes);
             },
-            BraceParsed::Incomplete(comp) => completion_scores.push(complete_score(&comp, &scores2, er)),
-        };
+        }
     }
-    er.part1(total_score, None);
-    completion_scores.sort();
-    er.part2(completion_scores[(completion_scores.len()-1)/2], None);
 }
 
-fn complete_score(comp: &str, scores: &HashMap<u8, i32>, er: &mut ExRunner) -> i64 {
-    let mut score: i64 = 0;
-    for c in comp.as_bytes() {
-        score *= 5;
-        if let Some(sc) = scores.get(c) {
-            score += *sc as i64;
-        } else {
-            er.debugln(&format!("Non-matching completion char: {}", *c as char));
+// a bracket that is open, and where it was opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Open {
+    pub offset: usize,
+    pub opener: char,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Check {
+    Ok,
+    // a closer that does not match the bracket that is open, if any
+    Corrupted { offset: usize, found: char, expected: Option<Open>, stack: Vec<Open> },
+    // brackets are still open at the end
+    Incomplete { stack: Vec<Open> },
+}
+
+// a change to a text at a byte offset of the original text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Insert(usize, char),
+    Delete(usize),
+    Replace(usize, char),
+}
+
+impl Edit {
+    pub fn offset(&self) -> usize {
+        match self {
+            Edit::Insert(o, _) | Edit::Delete(o) | Edit::Replace(o, _) => *o,
         }
     }
-    er.debugln(&format!("Completion score for {comp} = {score}"));
-    score
 }
 
-fn brace_matching(input: &String, braces: &HashMap<u8, u8>) -> BraceParsed {
-    let mut stack = Vec::new();
-    for c in input.as_bytes() {
-        if let Some(brmatch) = braces.get(c) {
-            stack.push(*brmatch);
-        } else if !stack.is_empty() && *c == stack[stack.len()-1] {
-            stack.pop();
-        } else {
-            return BraceParsed::FailChar(*c);
+// apply edits sorted by offset to the text they were made for
+pub fn apply(text: &str, edits: &[Edit]) -> String {
+    let mut out = String::new();
+    let mut edits = edits.iter().peekable();
+    for (offset, c) in text.char_indices().chain([(text.len(), '\0')]) {
+        let mut keep = offset < text.len();
+        while let Some(edit) = edits.next_if(|e| e.offset() == offset) {
+            match edit {
+                Edit::Insert(_, new) => out.push(*new),
+                Edit::Delete(_) => keep = false,
+                Edit::Replace(_, new) => {
+                    out.push(*new);
+                    keep = false;
+                },
+            }
+        }
+        if keep {
+            out.push(c);
         }
     }
-    if stack.is_empty() {
-        return BraceParsed::Ok
-    } else {
-        stack.reverse();
-        let completion = str::from_utf8(&stack).unwrap().to_string();
-        return BraceParsed::Incomplete(completion);
-    }
+    out
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+pub fn init_scores1() -> HashMap<char, i32> {
+    HashMap::from([(')', 3), (']', 57), ('}', 1197), ('>', 25137)])
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"[({(<(())[]>[[{[]{<()<>>
+pub fn init_scores2() -> HashMap<char, i32> {
+    HashMap::from([(')', 1), (']', 2), ('}', 3), ('>', 4)])
+}
+
+pub fn parse(input: &str) -> Result<Vec<String>, ParseError> {
+    Ok(Text::new(input).lines().map(|l| l.as_str().to_string()).collect())
+}
+
+// total syntax error score of the corrupted lines
+pub fn part1(lines: &[String]) -> i32 {
+    let brackets = Brackets::default();
+    let scores1 = init_scores1();
+    lines.iter().filter_map(|nav| match brackets.check(nav) {
+        Check::Corrupted { found, .. } => scores1.get(&found),
+        _ => None,
+    }).sum()
+}
+
+// middle completion score of the incomplete lines, 0 when no line is incomplete
+pub fn part2(lines: &[String]) -> i64 {
+    let brackets = Brackets::default();
+    let scores2 = init_scores2();
+    let mut completion_scores: Vec<i64> = lines.iter().filter_map(|nav| match brackets.check(nav) {
+        Check::Incomplete { stack } => Some(complete_score(&brackets.completion(&stack), &scores2)),
+        _ => None,
+    }).collect();
+    completion_scores.sort();
+    completion_scores.get(completion_scores.len().saturating_sub(1) / 2).copied().unwrap_or(0)
+}
+
+pub struct SyntaxScoring;
+
+impl Solver for SyntaxScoring {
+    type Input = Vec<String>;
+    type Answer1 = i32;
+    type Answer2 = i64;
+
+    const DAY: u32 = 10;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Syntax Scoring";
+    const EXAMPLE: &'static str = "[({(<(())[]>[[{[]{<()<>>
 [(()[<>])]({[<{<<[]>>(
 {([(<{}[<>[]}>{[]{[(<()>
 (((({<>}<{<{<>}{[]{[]{}
@@ -115,14 +342,95 @@ mod tests {
 [<(<(<(<{}))><([]([]()
 <{([([[(<>()){}]>(<<{{
 <{([{{}}[<[[[<>{}]]]>[]]
-".as_bytes())
+";
+
+    fn parse(input: &str) -> Result<Vec<String>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Vec<String>) -> i32 {
+        part1(input)
+    }
+
+    fn part2(input: &Vec<String>) -> i64 {
+        part2(input)
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&SyntaxScoring, input, er)
+}
+
+// score a completion string. Characters without a score only multiply.
+pub fn complete_score(comp: &str, scores: &HashMap<char, i32>) -> i64 {
+    let mut score: i64 = 0;
+    for c in comp.chars() {
+        score *= 5;
+        if let Some(sc) = scores.get(&c) {
+            score += *sc as i64;
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(SyntaxScoring::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 10 - syntax scoring".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 10 - syntax scoring".to_string(), &SyntaxScoring, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("26397".to_string()));
         assert_eq!(er.answ()[1], Some("288957".to_string()));
+        assert_eq!(part2(&["()".to_string()]), 0);
+    }
+
+    #[test]
+    fn test_check() {
+        let brackets = Brackets::default();
+        assert_eq!(brackets.check("[({}<>)]"), Check::Ok);
+        assert_eq!(brackets.check("[(x]"), Check::Corrupted { offset: 3, found: ']', expected: Some(Open { offset: 1, opener: '(' }),
+            stack: vec![Open { offset: 0, opener: '[' }, Open { offset: 1, opener: '(' }] });
+        assert!(matches!(brackets.check("[<"), Check::Incomplete { stack } if brackets.completion(&stack) == ">]"));
+        let quotes = Brackets::parse("()«»").unwrap();
+        assert!(matches!(quotes.check("«(»"), Check::Corrupted { offset: 3, found: '»', .. }));
+        assert!(Brackets::parse("(()").is_err());
+        assert!(Brackets::parse("()((").is_err());
+    }
+
+    #[test]
+    fn test_repair() {
+        let brackets = Brackets::default();
+        let line = "{([(<{}[<>[]}>{[]{[(<()>";
+        let edits = brackets.repair(line);
+        assert_eq!(edits[0], Edit::Replace(12, ']'));
+        let fixed = apply(line, &edits);
+        assert_eq!(brackets.check(&fixed), Check::Ok);
+        assert_eq!(brackets.repair("(]"), [Edit::Replace(1, ')')]);
+        assert_eq!(brackets.repair("(a))"), [Edit::Delete(3)]);
+        assert_eq!(apply("[(])", &brackets.repair("[(])")), "[()]");
+        // a scan that fixes the first bad closer first would replace both ] and add a )
+        assert_eq!(brackets.repair("(]])"), [Edit::Replace(1, '[')]);
+        assert_eq!(brackets.repair("([)]").len(), 2);
+        assert_eq!(brackets.repair("(<<"), [Edit::Replace(2, '>'), Edit::Insert(3, ')')]);
+        for line in SyntaxScoring::EXAMPLE.lines() {
+            assert_eq!(brackets.check(&apply(line, &brackets.repair(line))), Check::Ok);
+        }
+    }
+
+    #[test]
+    fn test_lint() {
+        let errors = Brackets::default().lint("fn f() {\n    g(x];\n");
+        assert_eq!(errors, [
+            ParseError::new("Unexpected ], expected ) to close ( from line 2, column 6; replace it with )").at_line(2).at_col(8).with_text("]"),
+            ParseError::new("{ is never closed, insert } at the end").at_line(1).at_col(8).with_text("{"),
+        ]);
     }
 }
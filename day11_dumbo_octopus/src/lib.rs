@@ -1,44 +1,41 @@
-use exrunner::ExRunner;
+use exrunner::{Solver, ExRunner, ParseError, Grid, Rgb, Viz, Screen};
 use std::io::BufRead;
 
-#[derive(Debug)]
-struct Octogy {
-    level: u8,
-    flashed: bool,
+#[derive(Debug, Clone)]
+pub struct Octogy {
+    pub level: u8,
+    pub flashed: bool,
 }
 
-fn parse(input: impl BufRead) -> Vec<Vec<Octogy>> {
-    input.lines().map(|l| l.unwrap().as_bytes().iter().map(|c| Octogy { level: *c - b'0', flashed: false }).collect()).collect()
+pub fn parse(input: &str) -> Result<Grid<Octogy>, ParseError> {
+    Ok(Grid::parse_digits(input)?.map(|level| Octogy { level: *level, flashed: false }))
 }
 
-fn do_one_step(octopii: &mut Vec<Vec<Octogy>>) -> i32 {
+// run one step of the simulation, returns the number of flashes
+pub fn do_one_step(octopii: &mut Grid<Octogy>) -> i32 {
     let mut process_flash: Vec<(usize, usize)> = Vec::new();
-    for y in 0 .. octopii.len() {
-        for x in 0 .. octopii[y].len() {
-            octopii[y][x].level += 1;
-            if octopii[y][x].level > 9 {
-                octopii[y][x] = Octogy { level: 0, flashed: true };
+    for y in 0 .. octopii.height() {
+        for x in 0 .. octopii.width() {
+            let octopus = &mut octopii[(x, y)];
+            octopus.level += 1;
+            if octopus.level > 9 {
+                *octopus = Octogy { level: 0, flashed: true };
                 process_flash.push((x, y));
             } else {
-                octopii[y][x].flashed = false;
+                octopus.flashed = false;
             }
         }
     }
     let mut flashes = 0;
-    while !process_flash.is_empty() {
+    while let Some((x, y)) = process_flash.pop() {
         flashes += 1;
-        let (x, y) = process_flash.pop().unwrap();
-        for (dx, dy) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)] {
-            if (y as i32) + dy < 0 || (y as i32) + dy >= octopii.len() as i32 || (x as i32) + dx < 0 || (x as i32) + dx >= octopii[y].len() as i32 {
-                continue;
-            }
-            let nx = ((x as i32) + dx) as usize;
-            let ny = ((y as i32) + dy) as usize;
-            if !octopii[ny][nx].flashed {
-                octopii[ny][nx].level += 1;
-                if octopii[ny][nx].level > 9 {
-                    octopii[ny][nx] = Octogy { level: 0, flashed: true };
-                    process_flash.push((nx, ny));
+        for n in octopii.neighbours8(x, y) {
+            let octopus = &mut octopii[n];
+            if !octopus.flashed {
+                octopus.level += 1;
+                if octopus.level > 9 {
+                    *octopus = Octogy { level: 0, flashed: true };
+                    process_flash.push(n);
                 }
             }
         }
@@ -46,34 +43,66 @@ fn do_one_step(octopii: &mut Vec<Vec<Octogy>>) -> i32 {
     flashes
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let mut octopii = parse(input);
-    er.parse_done();
-    let mut flashes: i32 = 0;
-    for _ in 1..=100 {
-        flashes += do_one_step(&mut octopii);
+// the state after a step of the simulation
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub step: i32,
+    pub flashes: i32,
+    pub octopii: Grid<Octogy>,
+}
+
+// run the simulation, one step at a time, with a snapshot of every step for play and visualize.
+// Never ends.
+pub fn steps(octopii: &Grid<Octogy>) -> impl Iterator<Item = Step> {
+    let mut octopii = octopii.clone();
+    (1..).map(move |step| {
+        let flashes = do_one_step(&mut octopii);
+        Step { step, flashes, octopii: octopii.clone() }
+    })
+}
+
+// number of flashes in the first 100 steps
+pub fn part1(octopii: &Grid<Octogy>) -> i32 {
+    let mut octopii = octopii.clone();
+    let mut flashes = 0;
+    for step in 1..=100 {
+        let step_flashes = do_one_step(&mut octopii);
+        exrunner::debug!("step {step}: {step_flashes} flashes");
+        exrunner::trace!("step {step}:\n{}", octopii.render(|o| char::from(b'0' + o.level)));
+        flashes += step_flashes;
     }
-    let mut step = 100;
-    let total_octopii: i32 = (octopii.len() * octopii[0].len()) as i32;
-    er.debugln(&format!("Got: {:?}", octopii));
-    er.part1(flashes, Some("Number of flashes"));
-    loop {
+    flashes
+}
+
+// first step during which all octopuses flash
+pub fn part2(octopii: &Grid<Octogy>) -> i32 {
+    let total_octopii = octopii.len() as i32;
+    let mut octopii = octopii.clone();
+    let mut step = 1;
+    while do_one_step(&mut octopii) != total_octopii {
         step += 1;
-        if do_one_step(&mut octopii) == total_octopii {
-            er.part2(step, Some("All flashing at once on step"));
-            break;
-        }
     }
+    step
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+// the octopuses as a frame: flashing ones in white, the others darker the lower their energy level
+fn draw(octopii: &Grid<Octogy>) -> Grid<Rgb> {
+    octopii.map(|o| if o.flashed { Rgb::WHITE } else { Rgb(0, 0, 20 + o.level * 20) })
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"5483143223
+pub struct DumboOctopus;
+
+impl Solver for DumboOctopus {
+    type Input = Grid<Octogy>;
+    type Answer1 = i32;
+    type Answer2 = i32;
+
+    const DAY: u32 = 11;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Dumbo Octopus";
+    const LABEL1: Option<&'static str> = Some("Number of flashes");
+    const LABEL2: Option<&'static str> = Some("All flashing at once on step");
+    const EXAMPLE: &'static str = "5483143223
 2745854711
 5264556173
 6141336146
@@ -83,14 +112,79 @@ mod tests {
 6882881134
 4846848554
 5283751526
-".as_bytes())
+";
+
+    fn parse(input: &str) -> Result<Grid<Octogy>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Grid<Octogy>) -> i32 {
+        part1(input)
+    }
+
+    fn part2(input: &Grid<Octogy>) -> i32 {
+        part2(input)
+    }
+
+    // the energy levels per step, with the flashing octopuses in white, until they all flash at once
+    fn play(input: &Grid<Octogy>) -> Box<dyn Iterator<Item = Screen> + '_> {
+        let total_octopii = input.len() as i32;
+        // stop after the step they all flash, or after 1000 steps when that does not happen
+        let mut all_flashed = false;
+        let until_all_flash = steps(input).take(1000).take_while(move |s| {
+            let more = !all_flashed;
+            all_flashed = s.flashes == total_octopii;
+            more
+        });
+        Box::new(until_all_flash.map(|s| {
+            let mut screen = Screen::new(format!("Step {}: {} flashes", s.step, s.flashes), s.octopii.width(), s.octopii.height());
+            for ((x, y), o) in s.octopii.iter() {
+                let colour = if o.flashed { Rgb::WHITE } else { Rgb(40, 40, 80 + o.level * 19) };
+                screen.text(x, y, &o.level.to_string(), colour);
+            }
+            screen
+        }))
+    }
+
+    // a frame per step, until they all flash at once
+    fn visualize(input: &Grid<Octogy>, viz: &mut Viz) {
+        viz.frame(|| draw(input));
+        for s in steps(input) {
+            viz.frame(|| draw(&s.octopii));
+            if s.flashes == input.len() as i32 || viz.is_full() {
+                break;
+            }
+        }
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&DumboOctopus, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(DumboOctopus::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 11 - dumbo octopus".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 11 - dumbo octopus".to_string(), &DumboOctopus, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("1656".to_string()));
         assert_eq!(er.answ()[1], Some("195".to_string()));
     }
+
+    #[test]
+    fn test_play() {
+        let input = parse(DumboOctopus::EXAMPLE).unwrap();
+        let screens: Vec<Screen> = DumboOctopus::play(&input).collect();
+        assert_eq!(screens.len(), 195);
+        assert_eq!(screens[194].caption, "Step 195: 100 flashes");
+    }
 }
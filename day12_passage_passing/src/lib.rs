@@ -1,102 +1,226 @@
-use exrunner::ExRunner;
-use std::{io::BufRead, collections::HashMap};
+use exrunner::{Solver, ExRunner, ParseError, Text};
+use graph::{Graph, Adjacency, Labels, NodeId};
+use std::io::BufRead;
 
+// the caves and the passages between them
 #[derive(Debug)]
-struct Cave {
-    to: Vec<String>,
-}
-
-fn parse(input: impl BufRead) -> HashMap<String, Cave> {
-    let mut ret = HashMap::new();
-    for l in input.lines() {
-        let lstr = l.expect("Error reading input");
-        let (a, b) = lstr.split_once('-').expect("Input lines should contain - char");
-        assert_ne!(a, b, "Caves cannot connect to themselves");
-        for (one, two) in [ (a, b), (b, a) ] {
-            ret.entry(one.to_string())
-                .and_modify(|cave: &mut Cave| cave.to.push(two.to_string()))
-                .or_insert(Cave{ to: vec![two.to_string()]});
+pub struct CaveSystem {
+    pub passages: Adjacency,
+    pub names: Labels<String>,
+    pub start: NodeId,
+    pub end: NodeId,
+}
+
+pub fn parse(input: &str) -> Result<CaveSystem, ParseError> {
+    let mut names = Labels::new();
+    let mut edges = Vec::new();
+    for line in Text::new(input).lines() {
+        let (a, b) = line.pair("-")?;
+        if a.as_str() == b.as_str() {
+            return Err(b.err("Caves cannot connect to themselves"));
+        }
+        // there would be endless routes going back and forth
+        if !is_small(a.as_str()) && !is_small(b.as_str()) {
+            return Err(line.err("Big caves cannot connect to each other"));
         }
+        edges.push((names.id(a.as_str().to_string()), names.id(b.as_str().to_string()), 1));
     }
-    ret
+    let (start, end) = match (names.get(&"start".to_string()), names.get(&"end".to_string())) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return Err(ParseError::new("Input should contain a start and an end cave")),
+    };
+    if (0..names.len()).filter(|n| is_small(names.label(*n))).count() > 64 {
+        return Err(ParseError::new("Input can have at most 64 small caves"));
+    }
+    exrunner::info!("{} caves, {} passages", names.len(), edges.len());
+    Ok(CaveSystem { passages: Adjacency::from_undirected_edges(names.len(), edges), names, start, end })
 }
 
-#[derive(Debug)]
-struct Route {
-    small_twice: bool,
-    route: String,
+// small caves are written in lowercase, and can only be visited once
+fn is_small(name: &str) -> bool {
+    name.chars().all(|c| c.is_ascii_lowercase())
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let caves = parse(input);
-    er.parse_done();
-    // routes will contain the list of possible routes, starts out with just "start".
-    // routes that do not work out are replace by None
-    // if we visited a small room twice, the string starts with "!"
-    let mut routes = vec![Some(Route{ small_twice: false, route: "start".to_string()})];
-    let mut path1: usize = 0;
-    let mut path2: usize = 0;
-    while !routes.is_empty() {
-        let mut addroutes = Vec::new();
-        let mut somes: usize = 0;
-        let mut nones: usize = 0;
-        for maybe_r in &mut routes {
-            if maybe_r.is_none() {
+// which routes count
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rules {
+    // the number of small caves a route may visit twice
+    pub twice: usize,
+    // a route may pass through start again, counting as a visit of a small cave
+    pub revisit_start: bool,
+    // a route may go on after reaching end, and end again later
+    pub revisit_end: bool,
+}
+
+// The small caves visited by a route, as bitmasks of caves visited at least once and twice.
+// Big caves are not counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Visited {
+    once: u64,
+    twice: u64,
+}
+
+// walks routes through the caves under the rules
+struct Walker<'a> {
+    caves: &'a CaveSystem,
+    rules: Rules,
+    // the bit of every small cave
+    bits: Vec<Option<u64>>,
+}
+
+impl<'a> Walker<'a> {
+    fn new(caves: &'a CaveSystem, rules: Rules) -> Walker<'a> {
+        let mut small = 0;
+        let bits = (0..caves.names.len()).map(|n| is_small(caves.names.label(n)).then(|| {
+            small += 1;
+            1 << (small - 1)
+        })).collect();
+        Walker { caves, rules, bits }
+    }
+
+    fn start(&self) -> Visited {
+        Visited { once: self.bits[self.caves.start].unwrap_or(0), twice: 0 }
+    }
+
+    // the visited caves after going to a cave, None if the rules do not allow it
+    fn enter(&self, to: NodeId, visited: Visited) -> Option<Visited> {
+        if to == self.caves.start && !self.rules.revisit_start {
+            return None;
+        }
+        let Some(bit) = self.bits[to] else {
+            return Some(visited);
+        };
+        if visited.once & bit == 0 {
+            Some(Visited { once: visited.once | bit, ..visited })
+        } else if visited.twice & bit == 0 && (visited.twice.count_ones() as usize) < self.rules.twice {
+            Some(Visited { twice: visited.twice | bit, ..visited })
+        } else {
+            None
+        }
+    }
+
+    // whether a route goes on after reaching a cave
+    fn goes_on(&self, node: NodeId) -> bool {
+        node != self.caves.end || self.rules.revisit_end
+    }
+}
+
+// The number of routes from start to end. Small caves may be visited once, and the rules allow
+// some of them a second visit. Start is a small cave that was visited already.
+pub fn count_paths(caves: &CaveSystem, rules: Rules) -> u64 {
+    let max_visits = |n: NodeId| if n == caves.start {
+        Some(rules.revisit_start as usize)
+    } else {
+        is_small(caves.names.label(n)).then_some(1)
+    };
+    caves.passages.count_paths(caves.start, caves.end, max_visits, rules.twice, rules.revisit_end) as u64
+}
+
+// Routes lists the routes from start to end one at a time, depth first. There can be very many,
+// count_paths counts them much faster.
+pub struct Routes<'a> {
+    walker: Walker<'a>,
+    neighbours: Vec<Vec<NodeId>>,
+    // the route so far: every cave with the visited caves on arrival and the next passage to try
+    route: Vec<(NodeId, Visited, usize)>,
+}
+
+impl<'a> Routes<'a> {
+    pub fn new(caves: &'a CaveSystem, rules: Rules) -> Routes<'a> {
+        let walker = Walker::new(caves, rules);
+        let neighbours = (0..caves.names.len()).map(|n| caves.passages.neighbours(n).collect()).collect();
+        let route = vec![(caves.start, walker.start(), 0)];
+        Routes { walker, neighbours, route }
+    }
+}
+
+impl Iterator for Routes<'_> {
+    type Item = Vec<NodeId>;
+
+    fn next(&mut self) -> Option<Vec<NodeId>> {
+        loop {
+            let (node, visited, next) = self.route.last_mut()?;
+            let Some(to) = self.neighbours[*node].get(*next).copied() else {
+                self.route.pop();
                 continue;
+            };
+            *next += 1;
+            let Some(visited) = self.walker.enter(to, *visited) else {
+                continue;
+            };
+            let mut found: Vec<NodeId> = self.route.iter().map(|r| r.0).collect();
+            found.push(to);
+            if self.walker.goes_on(to) {
+                self.route.push((to, visited, 0));
             }
-            let r = maybe_r.as_ref().unwrap();
-            let node = r.route.rsplit(',').next().unwrap();
-            if node == "end" {
-                panic!("Logic error, routes at end should not be on routes")
-            }
-            let cave = caves.get(node).expect(&format!("Error, bad cave {node}"));
-            for nxt in &cave.to {
-                // we cannot go back to start
-                if nxt == "start" {
-                    continue;
-                } else if nxt == "end" {
-                    //er.debugln(&format!("Route: {:?},{}", r, nxt));
-                    if r.small_twice {
-                        path2 += 1;
-                    } else {
-                        path1 += 1;
-                    }
-                    // remove this path from array now, so just continue
-                    continue;
-                }
-                // if it's a lowercase cave, make sure we haven't visited it already
-                let sml_twice = nxt.chars().all(|c| c.is_ascii_lowercase()) && r.route.contains(&(",".to_string() + nxt + ","));
-                // we can only visit one small room twice, so do not add this one if this is the second small room that we visit twice
-                if sml_twice && r.small_twice {
-                    continue;
-                }
-                let new_route = Route { small_twice: r.small_twice | sml_twice, route: r.route.clone() + "," + nxt };
-                // addroutes contains Option<String> just to make it compatible with routes. It never contains None.
-                addroutes.push(Some(new_route));
-            }
-            // replace this route with the first route found. Or any route. Or with "None" if there are no routes.
-            *maybe_r = addroutes.pop().unwrap_or(None);
-            if maybe_r.is_some() {
-                somes += 1;
-            } else {
-                nones += 1;
+            if to == self.walker.caves.end {
+                return Some(found);
             }
         }
-        // only prune if we inserted enough Nones
-        if nones * 10 > somes {
-            //er.debugln(&format!("Routes before prune: {}. Nones={}, Somes={}", routes.len(), nones, somes));
-            routes = routes.into_iter().filter(|x| x.is_some()).collect();
-            //er.debugln(&format!("Routes after prune: {}", routes.len()));
-        }
-        routes.append(&mut addroutes);
     }
-    er.part1(path1, Some("all routes with small caves once"));
-    er.part2(path1 + path2, Some("all possible routes"));
+}
+
+pub fn part1(caves: &CaveSystem) -> u64 {
+    count_paths(caves, Rules::default())
+}
+
+pub fn part2(caves: &CaveSystem) -> u64 {
+    count_paths(caves, Rules { twice: 1, ..Rules::default() })
+}
+
+pub struct PassagePathing;
+
+impl Solver for PassagePathing {
+    type Input = CaveSystem;
+    type Answer1 = u64;
+    type Answer2 = u64;
+
+    const DAY: u32 = 12;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Passage Pathing";
+    const LABEL1: Option<&'static str> = Some("all routes with small caves once");
+    const LABEL2: Option<&'static str> = Some("all possible routes");
+    const EXAMPLE: &'static str = "fs-end
+he-DX
+fs-he
+start-DX
+pj-DX
+end-zg
+zg-sl
+zg-pj
+pj-he
+RW-he
+fs-DX
+pj-RW
+zg-RW
+start-pj
+he-WI
+zg-he
+pj-fs
+start-RW
+";
+
+    fn parse(input: &str) -> Result<CaveSystem, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &CaveSystem) -> u64 {
+        part1(input)
+    }
+
+    fn part2(input: &CaveSystem) -> u64 {
+        part2(input)
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&PassagePathing, input, er)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use exrunner::ExRunner;
     use exrunner::ExCtx;
     use std::io::BufReader;
 
@@ -130,32 +254,12 @@ kj-dc
     }
 
     fn test_input3() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"fs-end
-he-DX
-fs-he
-start-DX
-pj-DX
-end-zg
-zg-sl
-zg-pj
-pj-he
-RW-he
-fs-DX
-pj-RW
-zg-RW
-start-pj
-he-WI
-zg-he
-pj-fs
-start-RW
-".as_bytes()
-        )
+        BufReader::new(PassagePathing::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve1() {
-        let er = ExRunner::run("day 12 - passage pathing".to_string(), solve, test_input1());
+        let er = ExRunner::run_solver("day 12 - passage pathing".to_string(), &PassagePathing, test_input1());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("10".to_string()));
         assert_eq!(er.answ()[1], Some("36".to_string()));
@@ -163,7 +267,7 @@ start-RW
 
     #[test]
     fn test_solve2() {
-        let er = ExRunner::run("day 12 - passage pathing".to_string(), solve, test_input2());
+        let er = ExRunner::run_solver("day 12 - passage pathing".to_string(), &PassagePathing, test_input2());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("19".to_string()));
         assert_eq!(er.answ()[1], Some("103".to_string()));
@@ -171,11 +275,27 @@ start-RW
 
     #[test]
     fn test_solve3() {
-        let ctx = ExCtx::new(solve, test_input3());
+        let ctx = ExCtx::from_solver(&PassagePathing, test_input3());
         let er = ctx.do_run("day 12 - passage pathing".to_string());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("226".to_string()));
         assert_eq!(er.answ()[1], Some("3509".to_string()));
     }
 
+    #[test]
+    fn test_routes() {
+        let caves = parse("start-A\nstart-b\nA-c\nA-b\nb-d\nA-end\nb-end\n").unwrap();
+        let names = |route: Vec<NodeId>| route.iter().map(|n| caves.names.label(*n).as_str()).collect::<Vec<_>>().join(",");
+        let routes: Vec<String> = Routes::new(&caves, Rules::default()).map(names).collect();
+        assert_eq!(routes.len(), 10);
+        assert!(routes.contains(&"start,A,c,A,b,A,end".to_string()));
+        // the iterator agrees with the count, whatever the rules
+        for twice in 0..=3 {
+            for (revisit_start, revisit_end) in [(false, false), (true, false), (false, true), (true, true)] {
+                let rules = Rules { twice, revisit_start, revisit_end };
+                assert_eq!(Routes::new(&caves, rules).count() as u64, count_paths(&caves, rules), "{rules:?}");
+            }
+        }
+        assert!(parse("start-A\nA-B\nB-end\n").is_err());
+    }
 }
@@ -1,161 +1,197 @@
-use exrunner::ExRunner;
+use exrunner::{Solver, ExRunner, ParseError, Grid, Text, Rgb, Viz, ocr};
+use std::collections::BTreeMap;
 use std::io::BufRead;
-use std::collections::HashMap;
-use std::str;
-use regex::Regex;
-use advent_of_code_ocr::*;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
-struct Point {
-    x: i32,
-    y: i32,
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
 }
 
-#[derive(Debug)]
-enum Fold {
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Fold {
     X(i32),
     Y(i32),
 }
 
 #[derive(Debug, Default)]
-struct OrigamiInstructions {
-    points: Vec<Point>,
-    folds: Vec<Fold>,
+pub struct OrigamiInstructions {
+    pub points: Vec<Point>,
+    pub folds: Vec<Fold>,
 }
 
-fn parse(input: impl BufRead) -> OrigamiInstructions {
+pub fn parse(input: &str) -> Result<OrigamiInstructions, ParseError> {
     let mut ret = OrigamiInstructions::default();
-    let origami_re = Regex::new(r"(?x)
-        ^(
-            (?<X>\d+),(?<Y>\d+)
-            |
-            fold\salong\s(?<fold>[xy])=(?<at>\d+)
-        )$
-        ").unwrap();
-    for l in input.lines() {
-        let line = l.expect("Error reading input");
-        if line.len() == 0 {
-            // skip empty lines
-            continue;
-        }
-        let cap = origami_re.captures(&line).expect("Cannot parse input");
-        if let Some(xm) = cap.name("X") {
-            let x: i32 = xm.as_str().parse().unwrap();
-            let y: i32 = cap["Y"].parse().unwrap();
-            ret.points.push(Point{x, y});
-        } else {
-            let at: i32 = cap["at"].parse().unwrap();
+    // points come first, then the fold instructions
+    for line in Text::new(input).lines().filter(|l| !l.is_empty()) {
+        if let Some(fold) = line.strip_prefix("fold along ") {
+            let (axis, at) = fold.pair("=")?;
+            let at: i32 = at.parse("Fold position should be a number")?;
             ret.folds.push(
-                match &cap["fold"] {
+                match axis.as_str() {
                     "x" => Fold::X(at),
                     "y" => Fold::Y(at),
-                    _ => panic!("Logic error in regex match"),
+                    _ => return Err(axis.err("Folds should be along x or y")),
                 }
             );
+        } else {
+            let (x, y) = line.pair(",")?;
+            let x: i32 = x.parse("Coordinates should be numbers")?;
+            let y: i32 = y.parse("Coordinates should be numbers")?;
+            ret.points.push(Point{x, y});
         }
     }
-    ret
+    if ret.folds.is_empty() {
+        return Err(ParseError::new("Input should contain fold instructions"));
+    }
+    Ok(ret)
 }
 
-fn do_fold(points: &mut HashMap<Point, ()>, f: &Fold) {
-    let mut replaces = Vec::new();
-    for p in points.keys() {
-        let opt_newp = match f {
-            Fold::X(limit) => {
-                if p.x > *limit {
-                    Some(Point{ x: 2 * *limit - p.x, y: p.y })
-                } else {
-                    None
-                }
-            },
-            Fold::Y(limit) => {
-                if p.y > *limit {
-                    Some(Point{ x: p.x, y: 2 * *limit - p.y })
-                } else {
-                    None
-                }
-            }
-        };
-        if let Some(newp) = opt_newp {
-            replaces.push(((*p).clone(), newp));
+impl Fold {
+    // where a point ends up. Points beyond the line go to the other side, even when that is
+    // past the edge of the paper because the line is not in the middle.
+    pub fn apply(&self, p: &Point) -> Point {
+        match *self {
+            Fold::X(at) if p.x > at => Point { x: 2 * at - p.x, y: p.y },
+            Fold::Y(at) if p.y > at => Point { x: p.x, y: 2 * at - p.y },
+            _ => p.clone(),
         }
     }
+}
+
+// Paper keeps track of where every original point is after the folds so far, and where they
+// were before each fold so the folds can be undone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paper {
+    original: Vec<Point>,
+    positions: Vec<Point>,
+    history: Vec<(Fold, Vec<Point>)>,
+}
+
+impl Paper {
+    pub fn new(points: &[Point]) -> Paper {
+        Paper { original: points.to_vec(), positions: points.to_vec(), history: Vec::new() }
+    }
+
+    pub fn fold(&mut self, f: Fold) {
+        let folded = self.positions.iter().map(|p| f.apply(p)).collect();
+        self.history.push((f, std::mem::replace(&mut self.positions, folded)));
+    }
+
+    // undo the last fold, returns it
+    pub fn unfold(&mut self) -> Option<Fold> {
+        let (f, positions) = self.history.pop()?;
+        self.positions = positions;
+        Some(f)
+    }
+
+    // the folds done so far, in order
+    pub fn folds(&self) -> impl Iterator<Item = Fold> + '_ {
+        self.history.iter().map(|(f, _)| *f)
+    }
 
-    for (p, newp) in replaces {
-        points.remove(&p);
-        points.insert(newp, ());
-    }
-}
-
-fn draw_points(points: Vec<Point>) -> String {
-    let (minx, maxx, miny, maxy) = points.iter().fold((i32::MAX, i32::MIN, i32::MAX, i32::MIN),
-        |(mut minx, mut maxx, mut miny, mut maxy), p| {
-            if p.x < minx {
-                minx = p.x;
-            }
-            if p.x > maxx {
-                maxx = p.x;
-            }
-            if p.y < miny {
-                miny = p.y;
-            }
-            if p.y > maxy {
-                maxy = p.y;
-            }
-            (minx, maxx, miny, maxy)
-        });
-    let mut canvas: Vec<Vec<u8>> = Vec::with_capacity((maxy-miny+1) as usize);
-    let mut xvec = Vec::with_capacity((maxx-minx+1) as usize);
-    for _x in minx..=maxx {
-        xvec.push(b'.');
-    }
-    for _y in miny..=maxy {
-        canvas.push(xvec.clone());
+    // the visible dots, sorted by y and then x
+    pub fn dots(&self) -> Vec<Point> {
+        let mut dots = self.positions.clone();
+        dots.sort_by_key(|p| (p.y, p.x));
+        dots.dedup();
+        dots
     }
+
+    // the original points that ended up on the same dot, for every dot with more than one
+    pub fn merged(&self) -> Vec<(Point, Vec<Point>)> {
+        let mut by_dot: BTreeMap<(i32, i32), Vec<Point>> = BTreeMap::new();
+        for (p, at) in self.original.iter().zip(&self.positions) {
+            by_dot.entry((at.y, at.x)).or_default().push(p.clone());
+        }
+        by_dot.into_iter().filter(|(_, ps)| ps.len() > 1).map(|((y, x), ps)| (Point { x, y }, ps)).collect()
+    }
+
+    // the dots as # on ., from the top left corner of the paper or of the dots, whichever is further out
+    pub fn render(&self) -> String {
+        draw_points(&self.dots())
+    }
+}
+
+pub fn draw_points(points: &[Point]) -> String {
+    let minx = points.iter().map(|p| p.x).min().unwrap_or(0).min(0);
+    let miny = points.iter().map(|p| p.y).min().unwrap_or(0).min(0);
+    let maxx = points.iter().map(|p| p.x).max().unwrap_or(0);
+    let maxy = points.iter().map(|p| p.y).max().unwrap_or(0);
+    let mut canvas: Grid<bool> = Grid::new((maxx-minx+1) as usize, (maxy-miny+1) as usize, false);
     for p in points {
-        canvas[(p.y - miny) as usize][(p.x - minx) as usize] = b'#';
+        canvas[((p.x - minx) as usize, (p.y - miny) as usize)] = true;
     }
-    canvas.into_iter().map(|xv| str::from_utf8(&xv).unwrap().to_string()).collect::<Vec<String>>().join("\n")
+    canvas.render(|dot| if *dot { '#' } else { '.' })
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let instr = parse(input);
-    er.parse_done();
-    // convert points to hashmap
-    let mut field: HashMap<Point, ()> = HashMap::new();
-    for p in instr.points {
-        field.insert(p, ());
-    }
-    let mut folds = instr.folds.iter();
-    do_fold(&mut field, &folds.next().unwrap());
-    er.part1(field.len(), None);
-    for f in folds {
-        do_fold(&mut field, f);
-    }
-    let disp = draw_points(field.into_keys().collect());
-    let screens = split_screen(&disp);
-    let mut answ2 = String::new();
-    for l in screens {
-        let one_char = parse_letter(&l);
-        if let Some(letter) = one_char {
-            answ2.push(letter);
-        } else {
-            er.debugln(&format!("Cannot parse letter:\n{}", l));
-            er.part2(disp, Some("Cannot parse letters from folded origami"));
-            return;
-        }
+// the paper as a frame, with the dots in white
+fn draw_paper(paper: &Paper) -> Grid<Rgb> {
+    let rows: Vec<Vec<Rgb>> = paper.render().lines()
+        .map(|l| l.chars().map(|c| if c == '#' { Rgb::WHITE } else { Rgb(0, 0, 60) }).collect())
+        .collect();
+    Grid::from_rows(rows).expect("Rendered rows have the same length")
+}
+
+// the paper rendered before folding, and after every fold
+pub fn stages(instr: &OrigamiInstructions) -> Vec<String> {
+    let mut paper = Paper::new(&instr.points);
+    let mut stages = vec![paper.render()];
+    for f in &instr.folds {
+        paper.fold(*f);
+        stages.push(paper.render());
     }
-    er.part2(answ2, Some("folded orgami output, parsed"));
+    stages
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+// Read the letters in a drawing. Folds that are not in the middle can leave the letters
+// mirrored, so all four ways of looking at the paper are tried and the one that reads best
+// wins. Letters that cannot be read are a ?. None when no letter can be read.
+pub fn read_letters(drawing: &str) -> Option<String> {
+    let rows: Vec<String> = drawing.lines().map(|l| l.to_string()).collect();
+    let flip = |rows: &[String]| rows.iter().map(|r| r.chars().rev().collect()).collect::<Vec<String>>();
+    let upside_down = |rows: &[String]| rows.iter().rev().cloned().collect::<Vec<String>>();
+    let views = [rows.clone(), flip(&rows), upside_down(&rows), upside_down(&flip(&rows))];
+    let score = |r: &ocr::Reading| r.glyphs.iter().filter(|g| g.letter().is_some()).count() as f64
+        + r.glyphs.iter().map(|g| g.confidence).sum::<f64>() / r.glyphs.len() as f64;
+    views.iter().filter_map(|view| ocr::read_text(&view.join("\n")))
+        .filter(|r| !r.text().chars().all(|c| c == '?'))
+        .fold(None, |best: Option<ocr::Reading>, r| match best {
+            Some(b) if score(&b) >= score(&r) => Some(b),
+            _ => Some(r),
+        })
+        .map(|r| r.text())
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"6,10
+// number of points visible after the first fold
+pub fn part1(instr: &OrigamiInstructions) -> usize {
+    let mut paper = Paper::new(&instr.points);
+    paper.fold(instr.folds[0]);
+    paper.dots().len()
+}
+
+// letters shown after all folds. If none can be recognised, the drawing is returned.
+pub fn part2(instr: &OrigamiInstructions) -> String {
+    let mut paper = Paper::new(&instr.points);
+    for f in &instr.folds {
+        paper.fold(*f);
+    }
+    let drawing = paper.render();
+    read_letters(&drawing).unwrap_or(drawing)
+}
+
+pub struct TransparentOrigami;
+
+impl Solver for TransparentOrigami {
+    type Input = OrigamiInstructions;
+    type Answer1 = usize;
+    type Answer2 = String;
+
+    const DAY: u32 = 13;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Transparent Origami";
+    const LABEL2: Option<&'static str> = Some("folded orgami output");
+    const EXAMPLE: &'static str = "6,10
 0,14
 9,10
 0,3
@@ -176,14 +212,89 @@ mod tests {
 
 fold along y=7
 fold along x=5
-".as_bytes()
-        )
+";
+
+    fn parse(input: &str) -> Result<OrigamiInstructions, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &OrigamiInstructions) -> usize {
+        part1(input)
+    }
+
+    fn part2(input: &OrigamiInstructions) -> String {
+        part2(input)
+    }
+
+    // the paper before folding, and after every fold
+    fn visualize(input: &OrigamiInstructions, viz: &mut Viz) {
+        let mut paper = Paper::new(&input.points);
+        viz.frame(|| draw_paper(&paper));
+        for f in &input.folds {
+            paper.fold(*f);
+            viz.frame(|| draw_paper(&paper));
+        }
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&TransparentOrigami, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(TransparentOrigami::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 13 - transparent origami".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 13 - transparent origami".to_string(), &TransparentOrigami, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("17".to_string()));
     }
+
+    #[test]
+    fn test_paper() {
+        let instr = parse(TransparentOrigami::EXAMPLE).unwrap();
+        let mut paper = Paper::new(&instr.points);
+        paper.fold(instr.folds[0]);
+        assert_eq!(paper.dots().len(), 17);
+        assert_eq!(paper.merged().len(), 1);
+        assert_eq!(paper.merged()[0], (Point { x: 8, y: 4 }, vec![Point { x: 8, y: 4 }, Point { x: 8, y: 10 }]));
+        paper.fold(instr.folds[1]);
+        assert_eq!(paper.render(), "#####\n#...#\n#...#\n#...#\n#####");
+        assert_eq!(paper.unfold(), Some(Fold::X(5)));
+        assert_eq!(paper.folds().collect::<Vec<_>>(), [Fold::Y(7)]);
+        paper.unfold();
+        assert_eq!(paper, Paper::new(&instr.points));
+        assert_eq!(stages(&instr).len(), 3);
+        // folding on a line that is not in the middle goes past the edge
+        let mut paper = Paper::new(&[Point { x: 0, y: 0 }, Point { x: 4, y: 0 }]);
+        paper.fold(Fold::X(1));
+        assert_eq!(paper.dots(), [Point { x: -2, y: 0 }, Point { x: 0, y: 0 }]);
+        assert_eq!(paper.render(), "#.#");
+    }
+
+    #[test]
+    fn test_read_letters() {
+        let h = ["#..#", "#..#", "####", "#..#", "#..#", "#..#"];
+        let e = ["####", "#...", "###.", "#...", "#...", "####"];
+        let drawing: Vec<String> = (0..6).map(|y| format!("{}.{}", e[y], h[y])).collect();
+        assert_eq!(read_letters(&drawing.join("\n")), Some("EH".to_string()));
+        // mirrored, with a letter that cannot be read
+        let drawing: Vec<String> = (0..6).map(|y| format!("####.{}.{}", h[y], e[y].chars().rev().collect::<String>())).collect();
+        assert_eq!(read_letters(&drawing.join("\n")), Some("EH?".to_string()));
+        assert_eq!(read_letters("#"), None);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(parse("6,10\n\nfold along z=5\n").err(),
+            Some(ParseError::new("Folds should be along x or y").at_line(3).at_col(12).with_text("z")));
+    }
 }
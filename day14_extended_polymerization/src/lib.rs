@@ -1,108 +1,183 @@
-use exrunner::ExRunner;
+use exrunner::{Solver, ExRunner, ParseError, Text};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::AddAssign;
 use std::io::BufRead;
-use std::collections::HashMap;
+use num_traits::{One, Zero};
+pub use num_bigint::BigUint;
 
 #[derive(Debug)]
-struct PolyIn {
-    polymers: String,
-    rules: HashMap<String, char>,
+pub struct PolyIn {
+    pub polymers: String,
+    pub rules: HashMap<(char, char), char>,
 }
 
-fn parse(input: impl BufRead) -> PolyIn {
-    let mut lines = input.lines();
-    let polymers = lines.next().expect("Input cannot be empty").expect("Error reading input");
+pub fn parse(input: &str) -> Result<PolyIn, ParseError> {
+    // the polymer template, then the insertion rules
+    let sections = Text::new(input).sections();
+    let polymers = sections.first().ok_or(ParseError::new("Input cannot be empty").at_line(1))?.trim();
+    if polymers.as_str().contains(char::is_whitespace) {
+        return Err(polymers.err("Polymer template should be a single line"));
+    }
     let mut rules = HashMap::new();
-    for l in lines {
-        let line = l.expect("Error reading input");
-        if line.len() == 0 {
-            continue;
-        }
-        let (from, to) = line.split_once("->").expect("Invalid input");
-        let k = from.trim().to_string();
-        assert_eq!(k.len(), 2, "Input pairs should be 2 chars");
-        rules.insert(k, to.trim().chars().next().expect("Invalid input, empty insertion"));
+    for line in sections.iter().skip(1).flat_map(|s| s.lines()) {
+        let (from, to) = line.pair("->")?;
+        let pair = match from.as_str().chars().collect::<Vec<char>>()[..] {
+            [a, b] => (a, b),
+            _ => return Err(from.err("Input pairs should be 2 chars")),
+        };
+        let ins = to.as_str().chars().next().ok_or_else(|| to.err("Invalid input, empty insertion"))?;
+        rules.insert(pair, ins);
     }
-    PolyIn { polymers, rules }
+    let polymers = polymers.as_str().to_string();
+    Ok(PolyIn { polymers, rules })
 }
 
-fn to_mutation(rules: HashMap<String, char>) -> HashMap<String, (String, String)> {
-    let mut ret = HashMap::new();
-    for (pair, ins) in rules {
-        let mut c = pair.chars();
-        let newpair1 = format!("{}{ins}", c.next().unwrap());
-        let newpair2 = format!("{ins}{}", c.next().unwrap());
-        ret.insert(pair, (newpair1, newpair2));
-    }
-    ret
+// number of every element in a polymer, in alphabetical order
+pub type Histogram<T> = BTreeMap<char, T>;
+
+// The polymerization works on the counts of pairs of neighbouring elements: on every step a pair
+// turns into two new pairs, whatever its place in the polymer. A pair without an insertion rule
+// stays as it is. Elements are numbered alphabetically, and pair (a, b) is number a * elements + b.
+#[derive(Debug)]
+pub struct Polymerizer {
+    elements: Vec<char>,
+    // the pairs every pair turns into
+    next: Vec<Vec<usize>>,
+    // the pairs of the template
+    start: Vec<usize>,
+    // the last element of the template, it is the only one that does not start a pair
+    last: usize,
 }
 
-fn count_pairs(p: &str) -> HashMap<String, i64> {
-    let mut ret = HashMap::new();
-    let mut prev = None;
-    for c in p.chars() {
-        if let Some(pc) = prev {
-            let pair = format!("{pc}{c}");
-            ret.entry(pair).and_modify(|x| *x = *x + 1).or_insert(1);
+impl Polymerizer {
+    pub fn new(polyin: &PolyIn) -> Polymerizer {
+        let elements: Vec<char> = polyin.polymers.chars()
+            .chain(polyin.rules.iter().flat_map(|(&(a, b), &c)| [a, b, c]))
+            .collect::<BTreeSet<char>>().into_iter().collect();
+        let n = elements.len();
+        let index = |c: char| elements.binary_search(&c).unwrap();
+        let next = (0..n * n).map(|p| {
+            let (a, b) = (elements[p / n], elements[p % n]);
+            match polyin.rules.get(&(a, b)) {
+                Some(&c) => vec![index(a) * n + index(c), index(c) * n + index(b)],
+                None => vec![p],
+            }
+        }).collect();
+        let template: Vec<usize> = polyin.polymers.chars().map(index).collect();
+        let start = template.windows(2).map(|w| w[0] * n + w[1]).collect();
+        Polymerizer { elements, next, start, last: *template.last().expect("Polymer template cannot be empty") }
+    }
+
+    // the element counts from the pair counts: every element starts a pair, except the last one.
+    // Every element gets an entry, also the ones that do not occur.
+    fn histogram_of<T>(&self, pairs: &[T]) -> Histogram<T>
+        where T: Zero + One + Clone + for<'a> AddAssign<&'a T>
+    {
+        let n = self.elements.len();
+        let mut counts = vec![T::zero(); n];
+        for (p, c) in pairs.iter().enumerate() {
+            counts[p / n] += c;
         }
-        prev = Some(c);
+        counts[self.last] += &T::one();
+        self.elements.iter().copied().zip(counts).collect()
     }
-    ret
-}
 
-fn do_polymerize(fcounts: HashMap<String, i64>, polyfreq: &HashMap<String, (String, String)>) -> HashMap<String, i64> {
-    let mut nfreq = HashMap::new();
-    for (pair, cnt) in fcounts {
-        let (npair1, npair2) = polyfreq.get(&pair).expect("Incomplete polymerization rules");
-        for np in [npair1, npair2] {
-            nfreq.entry(np.to_string()).and_modify(|c| *c = *c + cnt).or_insert(cnt);
+    // the exact element counts after a number of steps, calculated one step at a time.
+    // The polymer doubles in length every step, so u64 counts overflow after about 60 steps,
+    // use BigUint for more.
+    pub fn histogram<T>(&self, steps: u64) -> Histogram<T>
+        where T: Zero + One + Clone + for<'a> AddAssign<&'a T>
+    {
+        let mut pairs = vec![T::zero(); self.next.len()];
+        for p in &self.start {
+            pairs[*p] += &T::one();
+        }
+        for _ in 0..steps {
+            let mut next = vec![T::zero(); pairs.len()];
+            for (p, c) in pairs.iter().enumerate().filter(|(_, c)| !c.is_zero()) {
+                for np in &self.next[p] {
+                    next[*np] += c;
+                }
+            }
+            pairs = next;
         }
+        self.histogram_of(&pairs)
     }
-    nfreq
-}
 
-fn pair_to_polyfreq(pcounts: &HashMap<String, i64>, orig: &str) -> HashMap<char, i64> {
-    let mut ret: HashMap<char, i64> = HashMap::new();
-    for (pair, cnt) in pcounts {
-        let left = pair.chars().next().unwrap();
-        ret.entry(left).and_modify(|c| *c += *cnt).or_insert(*cnt);
+    // the element counts modulo a number after any number of steps, like 10^12. Raises the matrix
+    // of pair transitions to the power steps, by repeated squaring.
+    pub fn histogram_mod(&self, steps: u64, modulus: u32) -> Histogram<u64> {
+        assert!(modulus > 0, "Cannot count modulo 0");
+        let size = self.next.len();
+        let m = modulus as u64;
+        // transitions[to][from]: how many pairs `to` a pair `from` turns into in one step
+        let mut transitions = vec![vec![0; size]; size];
+        for (from, tos) in self.next.iter().enumerate() {
+            for to in tos {
+                transitions[*to][from] = (transitions[*to][from] + 1) % m;
+            }
+        }
+        let mut pairs = vec![0; size];
+        for p in &self.start {
+            pairs[*p] = (pairs[*p] + 1) % m;
+        }
+        let mut steps = steps;
+        while steps > 0 {
+            if steps & 1 == 1 {
+                pairs = transitions.iter().map(|row| dot(row, &pairs, m)).collect();
+            }
+            steps >>= 1;
+            if steps > 0 {
+                transitions = mat_square(&transitions, m);
+            }
+        }
+        let pairs: Vec<u128> = pairs.into_iter().map(u128::from).collect();
+        self.histogram_of(&pairs).into_iter().map(|(e, c)| (e, (c % m as u128) as u64)).collect()
     }
-    // the very last nucleotide in the polymer is not counted this way. Explicitly add that one too
-    let last = orig.chars().last().unwrap();
-    ret.entry(last).and_modify(|c| *c += 1).or_insert(1);
-    ret
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let polyin = parse(input);
-    let polyfreq = to_mutation(polyin.rules);
-    let mut pfreq = count_pairs(&polyin.polymers);
-    er.parse_done();
-    for _ in 1..=10 {
-        pfreq = do_polymerize(pfreq, &polyfreq);
-    }
-    let polycount = pair_to_polyfreq(&pfreq, &polyin.polymers);
-    er.debugln(&format!("Got: {:?}", polycount));
-    let min = polycount.values().min().unwrap();
-    let max = polycount.values().max().unwrap();
-    er.part1(*max - *min, Some(&format!("Max {}, min {}", *max, *min)));
-    for _ in 11..=40 {
-        pfreq = do_polymerize(pfreq, &polyfreq);
-    }
-    let polycount = pair_to_polyfreq(&pfreq, &polyin.polymers);
-    er.debugln(&format!("Got: {:?}", polycount));
-    let min = polycount.values().min().unwrap();
-    let max = polycount.values().max().unwrap();
-    er.part2(*max - *min, Some(&format!("Max {}, min {}", *max, *min)));
+// the dot product modulo m. All numbers are below m, which fits in 32 bits, so the products
+// fit in 64 bits and their sum in 128.
+fn dot(a: &[u64], b: &[u64], m: u64) -> u64 {
+    (a.iter().zip(b).map(|(x, y)| (x * y) as u128).sum::<u128>() % m as u128) as u64
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+fn mat_square(a: &[Vec<u64>], m: u64) -> Vec<Vec<u64>> {
+    let size = a.len();
+    let columns: Vec<Vec<u64>> = (0..size).map(|c| a.iter().map(|row| row[c]).collect()).collect();
+    a.iter().map(|row| columns.iter().map(|col| dot(row, col, m)).collect()).collect()
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"NNCB
+// difference between the most and least common element after the given number of steps.
+// Counted exactly, so any number of steps fits.
+pub fn max_min_diff(polyin: &PolyIn, steps: u64) -> BigUint {
+    let counts = Polymerizer::new(polyin).histogram::<BigUint>(steps);
+    // elements that only occur in the rules are not in the polymer
+    let present = counts.values().filter(|c| !c.is_zero());
+    present.clone().max().unwrap() - present.min().unwrap()
+}
+
+pub fn part1(polyin: &PolyIn) -> BigUint {
+    max_min_diff(polyin, 10)
+}
+
+pub fn part2(polyin: &PolyIn) -> BigUint {
+    max_min_diff(polyin, 40)
+}
+
+pub struct ExtendedPolymerization;
+
+impl Solver for ExtendedPolymerization {
+    type Input = PolyIn;
+    type Answer1 = BigUint;
+    type Answer2 = BigUint;
+
+    const DAY: u32 = 14;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Extended Polymerization";
+    const LABEL1: Option<&'static str> = Some("Most minus least common element after 10 steps");
+    const LABEL2: Option<&'static str> = Some("Most minus least common element after 40 steps");
+    const EXAMPLE: &'static str = "NNCB
 
 CH -> B
 HH -> N
@@ -120,15 +195,81 @@ BB -> N
 BC -> B
 CC -> N
 CN -> C
-".as_bytes())
+";
+
+    fn parse(input: &str) -> Result<PolyIn, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &PolyIn) -> BigUint {
+        part1(input)
+    }
+
+    fn part2(input: &PolyIn) -> BigUint {
+        part2(input)
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&ExtendedPolymerization, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(ExtendedPolymerization::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 14 - extended polymerization".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 14 - extended polymerization".to_string(), &ExtendedPolymerization, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("1588".to_string()));
         assert_eq!(er.answ()[1], Some("2188189693529".to_string()));
     }
 
+    #[test]
+    fn test_histogram() {
+        let polyin = parse(ExtendedPolymerization::EXAMPLE).unwrap();
+        let poly = Polymerizer::new(&polyin);
+        assert_eq!(poly.histogram::<u64>(10), Histogram::from([('B', 1749), ('C', 298), ('H', 161), ('N', 865)]));
+        // exact counts beyond u64: the template has 3 pairs, and every pair doubles every step
+        let big = poly.histogram::<BigUint>(100);
+        assert_eq!(big.values().sum::<BigUint>(), BigUint::from(3u32) * BigUint::from(2u32).pow(100) + 1u32);
+        assert_eq!(max_min_diff(&polyin, 100), big.values().max().unwrap() - big.values().min().unwrap());
+        // modular counts agree with the exact ones, also for a huge number of steps
+        let m = 1_000_000_007;
+        let exact40 = poly.histogram::<u64>(40);
+        assert_eq!(poly.histogram_mod(40, m as u32), exact40.iter().map(|(e, c)| (*e, c % m)).collect());
+        let total = poly.histogram_mod(1_000_000_000_000, m as u32).values().sum::<u64>() % m;
+        let mut pow2 = 1;
+        let (mut base, mut exp) = (2, 1_000_000_000_000u64);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                pow2 = pow2 * base % m;
+            }
+            base = base * base % m;
+            exp >>= 1;
+        }
+        assert_eq!(total, (3 * pow2 + 1) % m);
+    }
+
+    #[test]
+    fn test_incomplete_rules() {
+        // NN becomes NCN, the other pairs stay as they are: NCNCB
+        let polyin = parse("NNCB\n\nNN -> C\n").unwrap();
+        let poly = Polymerizer::new(&polyin);
+        assert_eq!(poly.histogram::<u64>(1), Histogram::from([('B', 1), ('C', 2), ('N', 2)]));
+        assert_eq!(max_min_diff(&polyin, 1), BigUint::from(1u32));
+    }
+
+    #[test]
+    #[should_panic(expected = "modulo 0")]
+    fn test_modulus_zero() {
+        Polymerizer::new(&parse(ExtendedPolymerization::EXAMPLE).unwrap()).histogram_mod(10, 0);
+    }
 }
@@ -0,0 +1,61 @@
+use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId, black_box};
+use std::collections::HashMap;
+use exrunner::{Solver, Rng, Grid};
+use day15_chiton::{Chiton, parse, least_risk_cost};
+
+// the recursive relaxation that day15 used before it moved to the graph crate, kept here to
+// compare against.
+fn legacy_least_risk_cost(field: &Grid<u8>) -> i32 {
+    let mut risk: Grid<Option<i32>> = Grid::new(field.width(), field.height(), None);
+    risk[(0, 0)] = Some(0);
+    legacy_walk_risk(field, &mut risk, 0, 0, false);
+    risk[(field.width()-1, field.height()-1)].unwrap()
+}
+
+fn legacy_walk_risk(field: &Grid<u8>, risk: &mut Grid<Option<i32>>, x: usize, y: usize, only_update: bool) {
+    let mut walkers = vec![(x, y)];
+    loop {
+        let mut new_walkers: HashMap<(usize, usize), ()> = HashMap::new();
+        for p in walkers {
+            let cur_risk = risk[p].unwrap();
+            for (tx, ty) in field.neighbours4(p.0, p.1) {
+                let new_risk = cur_risk + (field[(tx, ty)] as i32);
+                if let Some(old_risk) = risk[(tx, ty)] {
+                    if old_risk <= new_risk {
+                        continue;
+                    }
+                    risk[(tx, ty)] = Some(new_risk);
+                    if !only_update {
+                        legacy_walk_risk(field, risk, tx, ty, true);
+                    }
+                } else if only_update {
+                    continue;
+                } else {
+                    risk[(tx, ty)] = Some(new_risk);
+                }
+                new_walkers.insert((tx, ty), ());
+            }
+        }
+        walkers = new_walkers.into_keys().collect();
+        if walkers.is_empty() {
+            return;
+        }
+    }
+}
+
+// tile the generated 100x100 map like part 2 does, 5 and 25 times in both directions
+fn bench_pathfinding(c: &mut Criterion) {
+    let field = parse(&Chiton::generate(&mut Rng::new(15))).unwrap();
+    let mut group = c.benchmark_group("chiton_pathfinding");
+    group.sample_size(10);
+    for tiles in [5, 25] {
+        let big = field.tile(tiles, tiles, |risk, tx, ty| ((*risk as usize - 1 + tx + ty) % 9 + 1) as u8);
+        assert_eq!(least_risk_cost(&big), legacy_least_risk_cost(&big) as u64);
+        group.bench_with_input(BenchmarkId::new("graph", tiles), &big, |b, f| b.iter(|| least_risk_cost(black_box(f))));
+        group.bench_with_input(BenchmarkId::new("legacy", tiles), &big, |b, f| b.iter(|| legacy_least_risk_cost(black_box(f))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pathfinding);
+criterion_main!(benches);
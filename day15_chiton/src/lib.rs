@@ -1,155 +1,67 @@
-use exrunner::ExRunner;
+use exrunner::{Solver, ExRunner, ParseError, Text, Rng, Grid, Rgb, Viz};
 use std::io::BufRead;
-use std::collections::HashMap;
+use graph::{Graph, GridGraph, Cost, grid_node};
 
-fn parse(input: impl BufRead) -> Vec<Vec<u8>> {
-    input.lines().map(|l| {
-        l.expect("Error reading input").as_bytes().into_iter().map(|c| *c - b'0').collect() }
-    ).collect()
+// parse the risk map. It should be non-empty, all lines should be of equal length and every
+// risk level should be 1 to 9.
+pub fn parse(input: &str) -> Result<Grid<u8>, ParseError> {
+    let risk = |c: char| c.to_digit(10).filter(|d| *d > 0).map(|d| d as u8);
+    let field = Text::new(input).grid(risk, "Risk levels should be digits 1 to 9")?;
+    if field.is_empty() {
+        return Err(ParseError::new("Input should be non-empty"));
+    }
+    Ok(field)
 }
 
-fn least_risk_cost(field: &Vec<Vec<u8>>) -> i32 {
-    // initialize a known risk factor array
-    let mut risk: Vec<Vec<Option<i32>>> = Vec::new();
-    let xsize = field[0].len();
-    let ysize = field.len();
-    for y in 0 .. ysize {
-        assert_eq!(field[y].len(), xsize, "Input not square");
-        let mut riskrow: Vec<Option<i32>> = Vec::new();
-        riskrow.resize(xsize, None);
-        risk.push(riskrow);
-    }
-    // the starting position, upper left, is the beginning
-    risk[0][0] = Some(0);
-    // now walk the risk map
-    walk_risk(&field, &mut risk, 0, 0, false);
-    // the least risk path cost is now in the lower right corner.
-    risk[ysize-1][xsize-1].unwrap()
+// the lowest total risk of a path from the upper left to the lower right corner. The risk of
+// entering a position counts, the starting position is never entered.
+pub fn least_risk_cost(field: &Grid<u8>) -> Cost {
+    least_risk_path(field).0
 }
 
-// walk the risk array, updating where possible.
-// if only_update is true, only updates risks that are already set.
-fn walk_risk(field: &Vec<Vec<u8>>, risk: &mut Vec<Vec<Option<i32>>>, x: usize, y: usize, only_update: bool) {
-    let xsize = field[0].len();
-    let ysize = field.len();
-    let mut walkers = vec![(x, y)];
-    loop {
-        let mut new_walkers: HashMap<(usize, usize), ()> = HashMap::new();
-        for (x, y) in walkers {
-            let cur_risk = risk[y][x].unwrap();
-            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
-                if (x as i32) + dx < 0 || (y as i32) + dy < 0 {
-                    continue;
-                }
-                let tx = ((x as i32) + dx) as usize;
-                let ty = ((y as i32) + dy) as usize;
-                if tx >= xsize || ty >= ysize {
-                    continue;
-                }
-                let new_risk = cur_risk + (field[ty][tx] as i32);
-                if let Some(old_risk) = risk[ty][tx] {
-                    if old_risk <= new_risk {
-                        // this path is not better than what we had, so give up
-                        continue;
-                    }
-                    risk[ty][tx] = Some(new_risk);
-                    if !only_update {
-                        // this path is better than we had, so update everything below here ASAP
-                        // to prevent any workers out there from doing useless work.
-                        walk_risk(&field, risk, tx, ty, true);
-                    }
-                } else if only_update {
-                    // we're only updating, so continue
-                    continue;
-                } else {
-                    risk[ty][tx] = Some(new_risk);
-                }
-                new_walkers.insert((tx, ty), ());
-            }
-        }
-        walkers = new_walkers.into_keys().collect();
-        // keep walking until all walkers are done
-        if walkers.is_empty() {
-            return;
-        }
-    }
+// the least risk path itself, with its cost
+pub fn least_risk_path(field: &Grid<u8>) -> (Cost, Vec<(usize, usize)>) {
+    let graph = GridGraph::new(field, |_, to| Some(*to as Cost));
+    let goal = grid_node(field, field.width() - 1, field.height() - 1);
+    let (cost, path) = graph.dijkstra(0, goal).expect("Lower right corner should be reachable");
+    (cost, path.into_iter().map(|n| (n % field.width(), n / field.width())).collect())
 }
 
-fn field_plus_one(field: &Vec<Vec<u8>>) -> Vec<Vec<u8>> {
-    let mut ret = Vec::new();
-    for line in field {
-        let newline: Vec<u8> = line.iter().map(|c|
-            match *c {
-                9 => 1,
-                x => x + 1,
-            }
-        ).collect();
-        ret.push(newline);
+// the risk map in shades of gray, with the least risk path in red
+fn draw_path(field: &Grid<u8>) -> Grid<Rgb> {
+    let mut frame = field.map(|risk| Rgb::gray(255 - risk * 25));
+    for p in least_risk_path(field).1 {
+        frame[p] = Rgb::RED;
     }
-    ret
+    frame
 }
 
-fn append_field(big: &mut Vec<Vec<u8>>, add: &Vec<Vec<u8>>, y: usize) {
-    if y >= big.len() {
-        // we can just append to big
-        for l in add {
-            let newl: Vec<u8> = (*l).clone();
-            big.push(newl);
-        }
-    } else {
-        for i in 0 .. add.len() {
-            let mut newl = add[i].clone();
-            big[y+i].append(&mut newl);
-        }
-    }
+// tile the field 5 times in both directions, with the risk increasing by one for every tile
+// to the right or down, wrapping around from 9 back to 1.
+pub fn field_times_five(field: &Grid<u8>) -> Grid<u8> {
+    field.tile(5, 5, |risk, tx, ty| (risk - 1 + tx as u8 + ty as u8) % 9 + 1)
 }
 
-fn field_times_five(field: &Vec<Vec<u8>>) -> Vec<Vec<u8>> {
-    let mut bigfield = field.clone();
-    let ysize = field.len();
-    let mut lastfield = &bigfield;
-    // append copies of the input field to big field in this pattern:
-    // 0 1 2 3 4
-    // 1 2 3 4 5
-    // 2 3 4 5 6
-    // 3 4 5 6 7
-    // 4 5 6 7 8
-    // we already got 0
-    let mut extrafield;
-    for off in 1 ..= 8 {
-        extrafield = field_plus_one(lastfield);
-        for y in 0 ..= 4 {
-            if y > off || off - y >= 5 {
-                continue;
-            }
-            // append extrafield to bigfield at x*xsize, y*ysize.
-            // Note that we do not need the x size as we're always appending at the end of the array
-            append_field(&mut bigfield, &extrafield, y * ysize);
-        }
-        lastfield = &extrafield;
-    }
-    bigfield
+pub fn part1(field: &Grid<u8>) -> Cost {
+    least_risk_cost(field)
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let field = parse(input);
-    assert!(field.len() > 0, "Input should be non-empty");
-    assert!(field[0].len() > 0, "Input lines should be non-empty");
-    er.parse_done();
-    er.part1(least_risk_cost(&field), None);
-    let bigfield = field_times_five(&field);
-    //println!("bigfield:\n{}", bigfield.iter().map(|l| String::from_utf8(l.iter().map(|b| b + b'0').collect()).unwrap()).collect::<Vec<String>>().join("\n"));
-    er.part2(least_risk_cost(&bigfield), None);
+pub fn part2(field: &Grid<u8>) -> Cost {
+    let bigfield = field_times_five(field);
+    least_risk_cost(&bigfield)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+pub struct Chiton;
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"1163751742
+impl Solver for Chiton {
+    type Input = Grid<u8>;
+    type Answer1 = Cost;
+    type Answer2 = Cost;
+
+    const DAY: u32 = 15;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Chiton";
+    const EXAMPLE: &'static str = "1163751742
 1381373672
 2136511328
 3694931569
@@ -159,15 +71,64 @@ mod tests {
 3125421639
 1293138521
 2311944581
-".as_bytes())
+";
+
+    fn parse(input: &str) -> Result<Grid<u8>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Grid<u8>) -> Cost {
+        part1(input)
+    }
+
+    fn part2(input: &Grid<u8>) -> Cost {
+        part2(input)
+    }
+
+    // the path of part 1, and of part 2
+    fn visualize(input: &Grid<u8>, viz: &mut Viz) {
+        viz.frame(|| draw_path(input));
+        viz.frame(|| draw_path(&field_times_five(input)));
+    }
+
+    // a 100x100 risk map
+    fn generate(rng: &mut Rng) -> String {
+        let mut ret = String::new();
+        for _ in 0..100 {
+            for _ in 0..100 {
+                ret += &(1 + rng.below(9)).to_string();
+            }
+            ret += "\n";
+        }
+        ret
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&Chiton, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(Chiton::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 15 - chiton".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 15 - chiton".to_string(), &Chiton, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("40".to_string()));
         assert_eq!(er.answ()[1], Some("315".to_string()));
     }
 
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("19\n11\n").unwrap().len(), 4);
+        assert_eq!(parse("19\n10\n"), Err(ParseError::new("Risk levels should be digits 1 to 9").at_line(2).at_col(2).with_text("0")));
+    }
 }
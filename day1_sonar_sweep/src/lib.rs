@@ -1,4 +1,4 @@
-use exrunner::ExRunner;
+use exrunner::{Solver, ExRunner, ParseError, Text, Rng};
 use std::io::BufRead;
 
 fn count_increase(acc: (usize, Option<u32>), elem: &u32) -> (usize, Option<u32>) {
@@ -10,24 +10,30 @@ fn count_increase(acc: (usize, Option<u32>), elem: &u32) -> (usize, Option<u32>)
     (newcount, Some(*elem))
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let in_vec: Vec<u32> = input.lines()
-                    .map(|x| x.unwrap().parse::<u32>().expect("Input should be ints")).collect();
-    er.parse_done();
-    let part1 = in_vec.iter().fold((0, None), count_increase).0;
-    er.part1(part1, Some("number of times depth measurement increases"));
-    let part2 = (3..in_vec.len()).filter(|i| in_vec[*i-3] < in_vec[*i]).count();
-    er.part2(part2, None);
+pub fn parse(input: &str) -> Result<Vec<u32>, ParseError> {
+    Text::new(input).lines().map(|l| l.parse("Input should be ints")).collect()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+pub fn part1(depths: &[u32]) -> usize {
+    depths.iter().fold((0, None), count_increase).0
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"199
+pub fn part2(depths: &[u32]) -> usize {
+    (3..depths.len()).filter(|i| depths[*i-3] < depths[*i]).count()
+}
+
+pub struct SonarSweep;
+
+impl Solver for SonarSweep {
+    type Input = Vec<u32>;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    const DAY: u32 = 1;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Sonar Sweep";
+    const LABEL1: Option<&'static str> = Some("number of times depth measurement increases");
+    const EXAMPLE: &'static str = "199
 200
 208
 210
@@ -37,15 +43,59 @@ mod tests {
 269
 260
 263
-".as_bytes()
-        )
+";
+
+    fn parse(input: &str) -> Result<Vec<u32>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Vec<u32>) -> usize {
+        part1(input)
+    }
+
+    fn part2(input: &Vec<u32>) -> usize {
+        part2(input)
+    }
+
+    // a random walk of 2000 depths, like the real input
+    fn generate(rng: &mut Rng) -> String {
+        let mut depth: u64 = 150;
+        let mut ret = String::new();
+        for _ in 0..2000 {
+            depth = (depth + rng.below(21)).saturating_sub(8);
+            ret += &format!("{depth}\n");
+        }
+        ret
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&SonarSweep, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(SonarSweep::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_part12() {
-        let er = ExRunner::run("day 1 - sonar sweep".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 1 - sonar sweep".to_string(), &SonarSweep, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("7".to_string()));
         assert_eq!(er.answ()[1], Some("5".to_string()));
+        // solve runs the same solver as a plain function
+        let er = ExRunner::run("day 1 - sonar sweep".to_string(), solve, test_input());
+        assert_eq!(er.answ()[1], Some("5".to_string()));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(parse("199\n2x0\n"), Err(ParseError::new("Input should be ints").at_line(2).at_col(1).with_text("2x0")));
     }
 }
@@ -1,64 +1,115 @@
-use exrunner::ExRunner;
+use exrunner::{Solver, ExRunner, ParseError, Text};
 use std::io::BufRead;
 
-enum Movement {
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Movement {
     Forward(i64),
     Down(i64),
     Up(i64),
 }
 
-fn parse(input: impl BufRead) -> Vec<Movement> {
-    input.lines().map(|l| {
-        let line = l.expect("Error reading input");
-        let sp = line.find(char::is_whitespace).expect("Input line should contain space");
-        let amount: i64 = line[sp..].trim().parse().expect("Input should contain numbers");
-        match &line[..sp] {
-            "forward" => Movement::Forward(amount),
-            "down" => Movement::Down(amount),
-            "up" => Movement::Up(amount),
-            _ => panic!("Unknown movement type"),
+pub fn parse(input: &str) -> Result<Vec<Movement>, ParseError> {
+    Text::new(input).lines().map(|line| {
+        let (direction, amount) = line.key_value()?;
+        let amount: i64 = amount.parse("Input should contain numbers")?;
+        match direction.as_str() {
+            "forward" => Ok(Movement::Forward(amount)),
+            "down" => Ok(Movement::Down(amount)),
+            "up" => Ok(Movement::Up(amount)),
+            _ => Err(direction.err("Unknown movement type")),
         }
     }).collect()
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let ivec = parse(input);
-    er.parse_done();
+// returns final horizontal position and depth, interpreting up and down as depth changes
+pub fn position(moves: &[Movement]) -> (i64, i64) {
+    let mut h: i64 = 0;
+    let mut d: i64 = 0;
+    for m in moves {
+        match m {
+            Movement::Forward(a) => h += a,
+            Movement::Down(a) => d += a,
+            Movement::Up(a) => d -= a,
+        };
+    }
+    (h, d)
+}
+
+// returns final horizontal position and depth, interpreting up and down as aim changes
+pub fn position_aimed(moves: &[Movement]) -> (i64, i64) {
     let mut h: i64 = 0;
     let mut d: i64 = 0;
     let mut aim: i64 = 0;
-    let mut d2: i64 = 0;
-    for m in ivec {
+    for m in moves {
         match m {
-            Movement::Forward(a) => { h += a; d2 += aim * a },
-            Movement::Down(a) => { d += a; aim += a },
-            Movement::Up(a) => { d -= a; aim -= a },
+            Movement::Forward(a) => { h += a; d += aim * a },
+            Movement::Down(a) => aim += a,
+            Movement::Up(a) => aim -= a,
         };
     }
-    er.part1(h*d, Some(&format!("Position horizonatl={h}, depth={d}. Multiplied:")));
-    er.part2(h*d2, Some(&format!("part2 horizontal={h}, depth={d2}. Multiplied:")));
+    (h, d)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+pub fn part1(moves: &[Movement]) -> i64 {
+    let (h, d) = position(moves);
+    h * d
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"forward 5
+pub fn part2(moves: &[Movement]) -> i64 {
+    let (h, d) = position_aimed(moves);
+    h * d
+}
+
+pub struct Dive;
+
+impl Solver for Dive {
+    type Input = Vec<Movement>;
+    type Answer1 = i64;
+    type Answer2 = i64;
+
+    const DAY: u32 = 2;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Dive!";
+    const LABEL1: Option<&'static str> = Some("Position horizontal * depth:");
+    const LABEL2: Option<&'static str> = Some("part2 horizontal * depth:");
+    const EXAMPLE: &'static str = "forward 5
 down 5
 forward 8
 up 3
 down 8
 forward 2
-".as_bytes()
-        )
+";
+
+    fn parse(input: &str) -> Result<Vec<Movement>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Vec<Movement>) -> i64 {
+        part1(input)
+    }
+
+    fn part2(input: &Vec<Movement>) -> i64 {
+        part2(input)
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&Dive, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(Dive::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test() {
-        let er = ExRunner::run("day 2 - dive".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 2 - dive".to_string(), &Dive, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("150".to_string()));
         assert_eq!(er.answ()[1], Some("900".to_string()));
@@ -1,6 +1,7 @@
-use exrunner::ExRunner;
-use std::{io::BufRead, collections::HashMap};
+use exrunner::{Solver, ExRunner, ParseError, Text};
+use std::collections::HashMap;
 use std::str;
+use std::io::BufRead;
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 struct BinDigit {
@@ -8,7 +9,7 @@ struct BinDigit {
     digit: bool,
 }
 
-fn reduce_list_bitcrit<'a>(lines: &Vec<&'a [u8]>, bitpos: u32, most: bool) -> &'a [u8] {
+fn reduce_list_bitcrit<'a>(lines: &[&'a [u8]], bitpos: u32, most: bool) -> &'a [u8] {
     let mut occur: HashMap<u8, usize> = HashMap::new();
     for l in lines {
         let pos = (l.len() as i32) - 1 - (bitpos as i32);
@@ -25,7 +26,7 @@ fn reduce_list_bitcrit<'a>(lines: &Vec<&'a [u8]>, bitpos: u32, most: bool) -> &'
     let mut keep = if ones >= zeros { b'1' } else { b'0' };
     // if not the most, we keep the other value
     if !most {
-        keep = keep ^ 1u8;
+        keep ^= 1u8;
     }
     let mut reduced: Vec<&[u8]> = Vec::new();
     for l in lines {
@@ -41,20 +42,23 @@ fn reduce_list_bitcrit<'a>(lines: &Vec<&'a [u8]>, bitpos: u32, most: bool) -> &'
     }
 }
 
-pub fn solve(mut input: impl BufRead, er: &mut ExRunner) {
-    let mut inbuf: Vec<u8> = Vec::new();
-    input.read_to_end(&mut inbuf).expect("Error reading input file");
-    let lines: Vec<&[u8]> = inbuf.split(|f| *f == b'\n').collect();
-    er.parse_done();
+// parse the diagnostic report into lines of ascii '0' and '1' digits
+pub fn parse(input: &str) -> Result<Vec<Vec<u8>>, ParseError> {
+    Text::new(input).lines().filter(|l| !l.is_empty()).map(|l| {
+        match l.as_str().find(|c| c != '0' && c != '1') {
+            None => Ok(l.as_str().as_bytes().to_vec()),
+            Some(pos) => Err(l.from(pos).err("Invalid digit in input")),
+        }
+    }).collect()
+}
+
+// returns gamma and epsilon rate, the most and least common bits in every position
+pub fn rates(lines: &[Vec<u8>]) -> (u32, u32) {
     let mut appear: HashMap<BinDigit, usize> = HashMap::new();
     for l in lines.iter() {
         for (p, b) in l.iter().enumerate() {
             let weight: u32 = 1 << (l.len()-1-p);
-            let digit = match b {
-                b'0' => false,
-                b'1' => true,
-                _ => panic!("Invalid digit in input"),
-            };
+            let digit = *b == b'1';
             appear.entry(BinDigit{ weight, digit }).and_modify(|x| *x += 1).or_insert(1);
         }
     }
@@ -67,32 +71,50 @@ pub fn solve(mut input: impl BufRead, er: &mut ExRunner) {
         if *ones + *zeros == 0 {
             break;
         }
-        if *ones > *zeros {
-            most_common += weight;
-        } else if *ones < *zeros {
-            least_common += weight;
-        } else {
-            panic!("As many ones as zeros for {weight}, stop");
+        match ones.cmp(zeros) {
+            std::cmp::Ordering::Greater => most_common += weight,
+            std::cmp::Ordering::Less => least_common += weight,
+            std::cmp::Ordering::Equal => panic!("As many ones as zeros for {weight}, stop"),
         }
         weight <<= 1;
     }
-    er.part1(most_common as u64 * least_common as u64, Some(&format!("gamma rate={most_common}, epsilon rate={least_common}")));
-    let startweight = weight.trailing_zeros() - 1;
+    (most_common, least_common)
+}
+
+// returns oxygen generator and co2 scrubber ratings
+pub fn ratings(lines: &[Vec<u8>]) -> (i64, i64) {
+    let lines: Vec<&[u8]> = lines.iter().map(|l| l.as_slice()).collect();
+    let startweight = lines.iter().map(|l| l.len()).max().expect("Input cannot be empty") as u32 - 1;
     let oxygen = reduce_list_bitcrit(&lines, startweight, true);
     let co2 = reduce_list_bitcrit(&lines, startweight, false);
     let oxygen_i = i64::from_str_radix(str::from_utf8(oxygen).unwrap(), 2).unwrap();
     let co2_i = i64::from_str_radix(str::from_utf8(co2).unwrap(), 2).unwrap();
-    er.part2(oxygen_i * co2_i, Some(&format!("oxygen: {oxygen_i}, co2: {co2_i}")));
+    (oxygen_i, co2_i)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+pub fn part1(lines: &[Vec<u8>]) -> u64 {
+    let (gamma, epsilon) = rates(lines);
+    gamma as u64 * epsilon as u64
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"00100
+pub fn part2(lines: &[Vec<u8>]) -> i64 {
+    let (oxygen, co2) = ratings(lines);
+    oxygen * co2
+}
+
+pub struct BinaryDiagnostic;
+
+impl Solver for BinaryDiagnostic {
+    type Input = Vec<Vec<u8>>;
+    type Answer1 = u64;
+    type Answer2 = i64;
+
+    const DAY: u32 = 3;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Binary Diagnostic";
+    const LABEL1: Option<&'static str> = Some("gamma rate * epsilon rate");
+    const LABEL2: Option<&'static str> = Some("oxygen rating * co2 rating");
+    const EXAMPLE: &'static str = "00100
 11110
 10110
 10111
@@ -104,13 +126,38 @@ mod tests {
 11001
 00010
 01010
-".as_bytes()
-        )
+";
+
+    fn parse(input: &str) -> Result<Vec<Vec<u8>>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Vec<Vec<u8>>) -> u64 {
+        part1(input)
+    }
+
+    fn part2(input: &Vec<Vec<u8>>) -> i64 {
+        part2(input)
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&BinaryDiagnostic, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(BinaryDiagnostic::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 3 - binary diagnostic".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 3 - binary diagnostic".to_string(), &BinaryDiagnostic, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("198".to_string()));
         assert_eq!(er.answ()[1], Some("230".to_string()));
@@ -1,162 +1,254 @@
-use exrunner::ExRunner;
-use std::{io, io::BufRead, collections::{HashMap, hash_map::Entry}};
+use exrunner::{Solver, ExRunner, ParseError, Text, Rgb, Screen, Grid};
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
 
-#[derive(PartialEq, Debug)]
-struct Board {
-    numbers: [[u8; 5]; 5],
-    // rows and cols counts the number of marked numbers in each row, col
-    rows: [u8; 5],
-    cols: [u8; 5],
+// the numbers on the boards and in the draw
+pub type Number = u32;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Board {
+    pub numbers: Grid<Number>,
+    // the number of marked numbers in each row, column and diagonal
+    rows: Vec<usize>,
+    cols: Vec<usize>,
+    diagonals: [usize; 2],
 }
 
 impl Board {
-    fn parse(input: &str) -> Board {
-        let mut numbers = [[0u8; 5]; 5];
-        // make sure we have a proper number of rows
-        let mut ok_rows = false;
-        for (row, line) in input.split_terminator('\n').enumerate() {
-            if row >= numbers.len() {
-                panic!("Too many input rows for board in {line}");
-            } else if row == numbers.len() - 1 {
-                ok_rows = true;
-            }
-            let mut ok_cols = false;
-            for (col, item) in line.split_whitespace().enumerate() {
-                if col >= numbers[0].len() {
-                    panic!("Too many items in input row in {line}");
-                } else if col == numbers[0].len() - 1 {
-                    ok_cols = true;
-                }
-                numbers[row][col] = item.parse().expect("Input should be numeric");
+    // parse a board of rows of numbers, all rows of the same length
+    pub fn parse(input: Text) -> Result<Board, ParseError> {
+        let mut rows: Vec<Vec<Number>> = Vec::new();
+        for line in input.lines() {
+            let row = line.split_whitespace().map(|item| item.parse("Input should be numeric")).collect::<Result<Vec<_>, _>>()?;
+            if rows.first().is_some_and(|first| first.len() != row.len()) {
+                return Err(line.err("Rows should all have the same number of columns"));
             }
-            assert!(ok_cols, "Not enough columns");
+            rows.push(row);
         }
-        assert!(ok_rows, "Not enough rows");
-        Board {
-            numbers,
-            rows: [0; 5],
-            cols: [0; 5],
+        if rows.is_empty() || rows[0].is_empty() {
+            return Err(input.err("Board should not be empty"));
         }
+        let numbers = Grid::from_rows(rows)?;
+        Ok(Board { rows: vec![0; numbers.height()], cols: vec![0; numbers.width()], diagonals: [0; 2], numbers })
     }
 
-    fn iter(self: &Board) -> BoardIterator {
-        BoardIterator { b: self, cursor: [0, 0] }
+    // all the numbers on the board, row by row
+    pub fn iter(&self) -> impl Iterator<Item = Number> + '_ {
+        self.numbers.values().copied()
     }
-}
-
-struct BoardIterator<'a> {
-    b: &'a Board,
-    // cursor to implement an iterator over the numbers
-    cursor: [u8; 2],
-}
-
-// Iterate over all the numbers in the board
-impl<'a> Iterator for BoardIterator<'a> {
-    type Item = u8;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.cursor[1] as usize >= self.b.numbers.len() {
-            return None;
-        } else if self.cursor[0] as usize >= self.b.numbers[self.cursor[1] as usize].len() {
-            self.cursor[0] = 0;
-            self.cursor[1] += 1;
-            if self.cursor[1] as usize >= self.b.numbers.len() {
-                return None;
+    // mark the number at x, y. Returns whether that completes a row, column or, when the
+    // rules allow, a diagonal of a square board.
+    fn mark(&mut self, x: usize, y: usize, rules: &Rules) -> bool {
+        let size = self.numbers.width();
+        self.rows[y] += 1;
+        self.cols[x] += 1;
+        let mut complete = self.rows[y] == size || self.cols[x] == self.numbers.height();
+        if rules.diagonals && size == self.numbers.height() {
+            for (diagonal, on) in [x == y, x + y == size - 1].into_iter().enumerate() {
+                if on {
+                    self.diagonals[diagonal] += 1;
+                    complete |= self.diagonals[diagonal] == size;
+                }
             }
         }
-        let i: Self::Item = self.b.numbers[self.cursor[1] as usize][self.cursor[0] as usize];
-        self.cursor[0] += 1;
-        return Some(i);
+        complete
     }
 }
 
+// variations on how a board wins
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rules {
+    // a marked diagonal of a square board wins as well
+    pub diagonals: bool,
+}
+
 struct NumPos {
     board: usize,
-    row: u8,
-    col: u8,
+    x: usize,
+    y: usize,
 }
 
-struct PuzzleInput {
-    draw: Vec<u8>,
-    boards: Vec<Board>,
-    numpos: HashMap<u8, Vec<NumPos>>,
+pub struct PuzzleInput {
+    pub draw: Vec<Number>,
+    pub boards: Vec<Board>,
+    numpos: HashMap<Number, Vec<NumPos>>,
 }
 
 impl PuzzleInput {
-    fn parse(input: impl BufRead) -> PuzzleInput {
-        let input = io::read_to_string(input).expect("Error reading input");
-        let mut chunks = input.split("\n\n");
-        let draw: Vec<u8> = chunks.next().expect("Input cannot be empty").split(',')
-            .map(|x| x.parse().expect("Draw should be numbers")).collect();
-        let boards: Vec<Board> = chunks.map(|c| Board::parse(c)).collect();
-        let mut numpos: HashMap<u8, Vec<NumPos>> = HashMap::new();
+    pub fn parse(input: &str) -> Result<PuzzleInput, ParseError> {
+        // the draw comes first, then the boards, all separated by empty lines
+        let sections = Text::new(input).sections();
+        let (draw, boards) = sections.split_first().ok_or(ParseError::new("Input cannot be empty"))?;
+        let draw: Vec<Number> = draw.numbers(",", "Draw should be numbers")?;
+        let boards: Vec<Board> = boards.iter().map(|b| Board::parse(*b)).collect::<Result<_, _>>()?;
+        if boards.is_empty() {
+            return Err(ParseError::new("Input should contain boards"));
+        }
+        let mut numpos: HashMap<Number, Vec<NumPos>> = HashMap::new();
         for (board, b) in boards.iter().enumerate() {
-            for (row, r) in b.numbers.iter().enumerate() {
-                for (col, i) in r.iter().enumerate() {
-                    let e = numpos.entry(*i).or_insert(Vec::new());
-                    e.push(NumPos{ board, row: row as u8, col: col as u8});
-                }
+            for ((x, y), i) in b.numbers.iter() {
+                numpos.entry(*i).or_default().push(NumPos { board, x, y });
             }
         }
-        PuzzleInput { draw, boards, numpos }
+        Ok(PuzzleInput { draw, boards, numpos })
     }
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let mut pi = PuzzleInput::parse(input);
-    er.parse_done();
-    let mut num_drawn: HashMap<u8, ()> = HashMap::new();
-    let mut boards_won: HashMap<usize, ()> = HashMap::new();
-    'draw: for d in pi.draw {
+pub fn parse(input: &str) -> Result<PuzzleInput, ParseError> {
+    PuzzleInput::parse(input)
+}
+
+// a board that won
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Win {
+    pub board: usize,
+    // 1 for the first board to win. Boards that win with the same number are ranked by board.
+    pub place: usize,
+    pub score: u64,
+}
+
+// one number drawn in the game
+#[derive(Debug, Clone, PartialEq)]
+pub struct Draw {
+    pub number: Number,
+    // the boards that have the number and had it marked, none when it was drawn before
+    pub marked: Vec<usize>,
+    // the boards that won with this number
+    pub winners: Vec<Win>,
+}
+
+// Game plays bingo one number at a time, as an iterator of draws. It ends when the numbers
+// run out; once all boards have won the draws have no winners. The marked numbers can be
+// inspected between draws.
+pub struct Game<'a> {
+    pi: &'a PuzzleInput,
+    rules: Rules,
+    boards: Vec<Board>,
+    num_drawn: HashSet<Number>,
+    boards_won: HashSet<usize>,
+    next: usize,
+}
+
+impl<'a> Game<'a> {
+    pub fn new(pi: &'a PuzzleInput) -> Game<'a> {
+        Game::with_rules(pi, Rules::default())
+    }
+
+    pub fn with_rules(pi: &'a PuzzleInput, rules: Rules) -> Game<'a> {
+        Game { pi, rules, boards: pi.boards.clone(), num_drawn: HashSet::new(), boards_won: HashSet::new(), next: 0 }
+    }
+
+    pub fn boards(&self) -> &[Board] {
+        &self.boards
+    }
+
+    pub fn is_marked(&self, number: Number) -> bool {
+        self.num_drawn.contains(&number)
+    }
+
+    pub fn has_won(&self, board: usize) -> bool {
+        self.boards_won.contains(&board)
+    }
+}
+
+impl Iterator for Game<'_> {
+    type Item = Draw;
+
+    fn next(&mut self) -> Option<Draw> {
+        let d = *self.pi.draw.get(self.next)?;
+        self.next += 1;
+        let mut marked = Vec::new();
+        let mut complete = Vec::new();
         // beware of duplicate numbers
-        let num_entry = num_drawn.entry(d);
-        if let Entry::Vacant(_) = num_entry {
-            num_entry.or_insert(());
-            if let Some(nps) = pi.numpos.get(&d) {
-                for np in nps {
-                    pi.boards[np.board].rows[np.row as usize] += 1;
-                    pi.boards[np.board].cols[np.col as usize] += 1;
-                    if pi.boards[np.board].rows[np.row as usize] == 5 || pi.boards[np.board].cols[np.col as usize] == 5 {
-                        // We have a winner
-                        // Assume no other simultaneous winner exists
-                        if boards_won.is_empty() {
-                            // do part 1
-                            let unmarked_sum: u32 = pi.boards[np.board].iter().filter_map(|i| if num_drawn.contains_key(&i) { None } else { Some(i as u32) }).sum();
-                            er.part1(unmarked_sum*(d as u32), Some(&format!("unmarked_sum={unmarked_sum}, drawn={d}, board={}", np.board)));
-                        }
-                        boards_won.insert(np.board, ());
-                        if boards_won.len() == pi.boards.len() {
-                            let unmarked_sum: u32 = pi.boards[np.board].iter().filter_map(|i| if num_drawn.contains_key(&i) { None } else { Some(i as u32) }).sum();
-                            er.part2(unmarked_sum*(d as u32), Some("Score of last board to win"));
-                            break 'draw;
-                        }
-                    }
+        if self.num_drawn.insert(d) {
+            for np in self.pi.numpos.get(&d).into_iter().flatten() {
+                if self.boards[np.board].mark(np.x, np.y, &self.rules) {
+                    complete.push(np.board);
                 }
+                marked.push(np.board);
             }
         }
+        marked.dedup();
+        complete.sort_unstable();
+        complete.dedup();
+        let mut winners = Vec::new();
+        for board in complete {
+            if self.boards_won.insert(board) {
+                let unmarked_sum: u64 = self.boards[board].iter().filter(|i| !self.num_drawn.contains(i)).map(u64::from).sum();
+                winners.push(Win { board, place: self.boards_won.len(), score: unmarked_sum * d as u64 });
+            }
+        }
+        Some(Draw { number: d, marked, winners })
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+// the complete game: every draw, with the boards it marked and the boards that won
+pub fn game_log(pi: &PuzzleInput, rules: Rules) -> Vec<Draw> {
+    Game::with_rules(pi, rules).collect()
+}
 
-    #[test]
-    fn test_board() {
-        let b = Board::parse(
-"22 13 17 11  0
-8  2 23  4 24
-21  9 14 16  7
-6 10  3 18  5
-1 12 20 15 19
-");
-       assert_eq!(b, Board { numbers: [ [ 22, 13, 17, 11, 0 ], [8, 2, 23, 4, 24], [21, 9, 14, 16, 7], [6, 10, 3, 18, 5], [1, 12, 20, 15, 19]], rows: [0; 5], cols: [0; 5]});
+// the boards in the order they win
+pub fn wins(log: &[Draw]) -> impl Iterator<Item = &Win> {
+    log.iter().flat_map(|d| &d.winners)
+}
+
+// draw the boards next to each other, marked numbers in yellow, boards that won in green
+fn draw_game(game: &Game, caption: String) -> Screen {
+    // every number takes the width of the widest, plus a space
+    let cell = game.boards().iter().flat_map(|b| b.iter()).max().unwrap_or(0).to_string().len() + 1;
+    let (board_width, board_height) = game.boards().iter()
+        .fold((0, 0), |(w, h), b| (w.max(b.numbers.width() * cell + 1), h.max(b.numbers.height() + 1)));
+    // as many boards as fit in 80 columns next to each other
+    let per_row = (80 / board_width).max(1);
+    let rows = game.boards().len().div_ceil(per_row);
+    let mut screen = Screen::new(caption, per_row * board_width, rows * board_height);
+    for (i, b) in game.boards().iter().enumerate() {
+        let (bx, by) = ((i % per_row) * board_width, (i / per_row) * board_height);
+        for ((x, y), n) in b.numbers.iter() {
+            let colour = match (game.is_marked(*n), game.has_won(i)) {
+                (true, true) => Rgb(0, 255, 0),
+                (true, false) => Rgb(255, 220, 0),
+                (false, _) => Rgb::gray(120),
+            };
+            screen.text(bx + x * cell, by + y, &format!("{n:>width$}", width = cell - 1), colour);
+        }
     }
+    screen
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+// score of the first board to win
+pub fn part1(pi: &PuzzleInput) -> u64 {
+    Game::new(pi).flat_map(|d| d.winners).next().expect("No board ever wins").score
+}
+
+// score of the last board to win. When several boards win last, the one ranked last.
+pub fn part2(pi: &PuzzleInput) -> u64 {
+    let mut last = None;
+    // stop drawing once every board has won
+    for win in Game::new(pi).flat_map(|d| d.winners) {
+        let done = win.place == pi.boards.len();
+        last = Some(win);
+        if done {
+            break;
+        }
+    }
+    last.expect("No board ever wins").score
+}
+
+pub struct GiantSquid;
+
+impl Solver for GiantSquid {
+    type Input = PuzzleInput;
+    type Answer1 = u64;
+    type Answer2 = u64;
+
+    const DAY: u32 = 4;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Giant Squid";
+    const LABEL1: Option<&'static str> = Some("Score of first board to win");
+    const LABEL2: Option<&'static str> = Some("Score of last board to win");
+    const EXAMPLE: &'static str = "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
 
 22 13 17 11  0
  8  2 23  4 24
@@ -175,15 +267,103 @@ mod tests {
 18  8 23 26 20
 22 11 13  6  5
  2  0 12  3  7
-".as_bytes()
-        )
+";
+
+    fn parse(input: &str) -> Result<PuzzleInput, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &PuzzleInput) -> u64 {
+        part1(input)
+    }
+
+    fn part2(input: &PuzzleInput) -> u64 {
+        part2(input)
+    }
+
+    // the boards after every number drawn
+    fn play(input: &PuzzleInput) -> Box<dyn Iterator<Item = Screen> + '_> {
+        let mut game = Game::new(input);
+        let mut first = Some(draw_game(&game, "Start".to_string()));
+        Box::new(std::iter::from_fn(move || {
+            if let Some(screen) = first.take() {
+                return Some(screen);
+            }
+            let draw = game.next()?;
+            let winners: Vec<String> = draw.winners.iter().map(|w| format!("board {} wins with score {}", w.board + 1, w.score)).collect();
+            let caption = format!("Drawn {}{}{}", draw.number, if winners.is_empty() { "" } else { ": " }, winners.join(", "));
+            Some(draw_game(&game, caption))
+        }))
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&GiantSquid, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    #[test]
+    fn test_board() {
+        let b = Board::parse(Text::new(
+"22 13 17 11  0
+8  2 23  4 24
+21  9 14 16  7
+6 10  3 18  5
+1 12 20 15 19
+")).unwrap();
+        assert_eq!(b.numbers, Grid::from_rows(vec![vec![22, 13, 17, 11, 0], vec![8, 2, 23, 4, 24], vec![21, 9, 14, 16, 7], vec![6, 10, 3, 18, 5], vec![1, 12, 20, 15, 19]]).unwrap());
+        let b = Board::parse(Text::new("1000 2000 3000\n4 5 6\n")).unwrap();
+        assert_eq!((b.numbers.width(), b.numbers.height()), (3, 2));
+        assert!(Board::parse(Text::new("1 2 3\n4 5\n")).is_err());
+    }
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(GiantSquid::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 4 - giant squid".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 4 - giant squid".to_string(), &GiantSquid, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("4512".to_string()));
         assert_eq!(er.answ()[1], Some("1924".to_string()));
     }
+
+    #[test]
+    fn test_game() {
+        let pi = parse(GiantSquid::EXAMPLE).unwrap();
+        let mut game = Game::new(&pi);
+        let first_win = game.by_ref().find(|d| !d.winners.is_empty());
+        assert_eq!(first_win, Some(Draw { number: 24, marked: vec![0, 1, 2], winners: vec![Win { board: 2, place: 1, score: 4512 }] }));
+        assert!(game.is_marked(24) && !game.is_marked(10));
+        assert!(game.has_won(2) && !game.has_won(0));
+    }
+
+    #[test]
+    fn test_game_log() {
+        // boards 0 and 1 win together, board 2 only on a diagonal
+        let pi = parse("1,2,3,4,2\n\n1 2\n5 6\n\n7 8\n1 2\n\n1 9\n9 4\n").unwrap();
+        let log = game_log(&pi, Rules::default());
+        assert_eq!(log.len(), 5);
+        assert_eq!(log[1].marked, [0, 1]);
+        assert_eq!(log[1].winners, [Win { board: 0, place: 1, score: 22 }, Win { board: 1, place: 2, score: 30 }]);
+        assert!(log[4].marked.is_empty());
+        assert_eq!(wins(&log).count(), 2);
+        let log = game_log(&pi, Rules { diagonals: true });
+        assert_eq!(log.len(), 5);
+        assert_eq!(log[3].winners, [Win { board: 2, place: 3, score: 72 }]);
+        // the numbers are still drawn after all boards have won
+        assert_eq!(log[4], Draw { number: 2, marked: vec![], winners: vec![] });
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let e = parse("7,4,9\n\n1 2 3 4 5\n1 2 3 4 5\n1 2 3 4 5\n1 2 x 4 5\n1 2 3 4 5\n").err();
+        assert_eq!(e, Some(ParseError::new("Input should be numeric").at_line(6).at_col(5).with_text("x")));
+    }
 }
@@ -1,110 +1,293 @@
-use exrunner::ExRunner;
-use std::{io::BufRead, str::FromStr, collections::HashMap};
+use exrunner::{Solver, ExRunner, ParseError, Rng, Text, Grid, Rgb, Viz};
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
 
 #[derive(Debug, PartialEq, Eq, Hash)]
-struct Point {
-    x: i32,
-    y: i32,
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct ParsePointError;
-
-impl FromStr for Point {
-    type Err = ParsePointError;
-
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let (xstr, ystr) = input.split_once(',').ok_or(ParsePointError)?;
-        let x: i32 = xstr.trim().parse().map_err(|_| ParsePointError)?;
-        let y: i32 = ystr.trim().parse().map_err(|_| ParsePointError)?;
+impl Point {
+    // parse "x,y"
+    pub fn parse(input: Text) -> Result<Point, ParseError> {
+        let (xstr, ystr) = input.pair(",")?;
+        let x: i32 = xstr.parse("Coordinates should be numbers")?;
+        let y: i32 = ystr.parse("Coordinates should be numbers")?;
         Ok(Point {x, y})
     }
 }
 
 #[derive(Debug, PartialEq)]
-struct Line {
-    start: Point,
-    end: Point,
+pub struct Line {
+    pub start: Point,
+    pub end: Point,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct ParseLineError;
+impl Line {
+    // true for horizontal and vertical lines
+    pub fn is_axis_aligned(&self) -> bool {
+        self.start.x == self.end.x || self.start.y == self.end.y
+    }
+
+    // the smallest step from one point of the grid on the line to the next, and the number of steps
+    fn step(&self) -> ((i64, i64), i64) {
+        let (dx, dy) = (self.end.x as i64 - self.start.x as i64, self.end.y as i64 - self.start.y as i64);
+        let steps = gcd(dx.abs(), dy.abs());
+        if steps == 0 {
+            ((0, 0), 0)
+        } else {
+            ((dx / steps, dy / steps), steps)
+        }
+    }
+
+    // all points of the grid on the line, from start to end. Lines can have any slope.
+    pub fn points(&self) -> impl Iterator<Item = (i64, i64)> {
+        let ((dx, dy), steps) = self.step();
+        let (x, y) = (self.start.x as i64, self.start.y as i64);
+        (0..=steps).map(move |i| (x + i * dx, y + i * dy))
+    }
+
+    fn x_range(&self) -> (i64, i64) {
+        (self.start.x.min(self.end.x) as i64, self.start.x.max(self.end.x) as i64)
+    }
+
+    fn y_range(&self) -> (i64, i64) {
+        (self.start.y.min(self.end.y) as i64, self.start.y.max(self.end.y) as i64)
+    }
 
-impl FromStr for  Line {
-    type Err = ParseLineError;
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let (startstr, endstr) = input.split_once("->").ok_or(ParseLineError)?;
-        let start: Point = startstr.trim().parse().map_err(|_| ParseLineError)?;
-        let end: Point = endstr.trim().parse().map_err(|_| ParseLineError)?;
-        Ok(Line { start, end })
+    // parse "x1,y1 -> x2,y2"
+    pub fn parse(input: Text) -> Result<Line, ParseError> {
+        let (startstr, endstr) = input.pair("->")?;
+        Ok(Line { start: Point::parse(startstr)?, end: Point::parse(endstr)? })
     }
 }
 
-fn parse_input(input: impl BufRead) -> Vec<Line> {
-    input.lines().map(|l| l.expect("Error reading input").parse().expect("Error parsing input")).collect()
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
 }
 
-fn doublepoints(lines: &Vec<Line>, online: &mut HashMap<Point, bool>) -> i32 {
-    let mut double_points = 0;
+pub fn parse(input: &str) -> Result<Vec<Line>, ParseError> {
+    Text::new(input).lines().map(Line::parse).collect()
+}
+
+// for every number of lines, the number of points covered by exactly that many lines
+pub type Overlaps = BTreeMap<usize, u64>;
+
+// the number of points covered by at least n lines
+pub fn at_least(overlaps: &Overlaps, n: usize) -> u64 {
+    overlaps.range(n..).map(|(_, count)| count).sum()
+}
+
+// the largest area counted point by point, in cells
+pub const DENSE_CELLS: i64 = 1 << 24;
+
+// the lines through every point of the area the lines cover, with the top left corner of the
+// area. None when the area is larger than max_cells.
+pub fn coverage(lines: &[&Line], max_cells: i64) -> Option<(Grid<u32>, (i64, i64))> {
+    let (minx, maxx) = lines.iter().map(|l| l.x_range()).reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))?;
+    let (miny, maxy) = lines.iter().map(|l| l.y_range()).reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))?;
+    let (width, height) = (maxx - minx + 1, maxy - miny + 1);
+    if width.checked_mul(height).is_none_or(|cells| cells > max_cells) {
+        return None;
+    }
+    let mut count: Grid<u32> = Grid::new(width as usize, height as usize, 0);
+    for (x, y) in lines.iter().flat_map(|l| l.points()) {
+        count[((x - minx) as usize, (y - miny) as usize)] += 1;
+    }
+    Some((count, (minx, miny)))
+}
+
+// count the overlaps by visiting every point of every line, in a grid of the area they cover
+pub fn overlaps_dense(lines: &[&Line]) -> Option<Overlaps> {
+    let (count, _) = coverage(lines, DENSE_CELLS)?;
+    let mut overlaps = Overlaps::new();
+    for c in count.values().filter(|c| **c > 0) {
+        *overlaps.entry(*c as usize).or_default() += 1;
+    }
+    Some(overlaps)
+}
+
+// The lines on one infinite line through the grid, the carrier. Points on it are numbered
+// along the direction of the carrier, and covered in runs of the same number of lines.
+struct Carrier {
+    direction: (i64, i64),
+    // start, end (excluded) and number of lines of every run
+    runs: Vec<(i64, i64, usize)>,
+}
+
+impl Carrier {
+    // the number of a point along the carrier
+    fn position(&self, (x, y): (i64, i64)) -> i64 {
+        match self.direction {
+            (0, _) => y,
+            (a, _) => x.div_euclid(a),
+        }
+    }
+
+    fn lines_at(&self, p: (i64, i64)) -> usize {
+        let t = self.position(p);
+        let i = self.runs.partition_point(|r| r.1 <= t);
+        self.runs.get(i).filter(|r| r.0 <= t).map_or(0, |r| r.2)
+    }
+}
+
+// the direction of a line pointing right or down, and the number that tells its carrier apart
+// from the parallel ones. Single points are a horizontal line.
+fn carrier_key(line: &Line) -> ((i64, i64), i128) {
+    let ((a, b), _) = line.step();
+    let (a, b) = match (a, b) {
+        (0, 0) => (1, 0),
+        (a, b) if a < 0 || (a == 0 && b < 0) => (-a, -b),
+        ab => ab,
+    };
+    ((a, b), b as i128 * line.start.x as i128 - a as i128 * line.start.y as i128)
+}
+
+// The overlaps without visiting the points. Lines on the same carrier are swept along it, so
+// long lines cost no more than short ones. Then the points where carriers cross are found by
+// sweeping over x, and counted once with all the lines through them. That costs time for every
+// pair of lines with overlapping x and y ranges.
+pub fn overlaps_sparse(lines: &[&Line]) -> Overlaps {
+    let mut keys: HashMap<((i64, i64), i128), usize> = HashMap::new();
+    let mut carrier_lines: Vec<Vec<&Line>> = Vec::new();
+    let mut carrier_of = Vec::with_capacity(lines.len());
     for l in lines {
-        let mut x = l.start.x;
-        let mut y = l.start.y;
-        let x2 = l.end.x;
-        let y2 = l.end.y;
-        let dx; let dy;
-        if x == x2 {
-            dx = 0;
-            dy = if y > y2 { -1 } else { 1 };
-        } else if y == y2 {
-            dx = if x > x2 { -1 } else { 1 };
-            dy = 0;
-        } else {
-            dx = if x > x2 { -1 } else { 1 };
-            dy = if y > y2 { -1 } else { 1 };
-            // make sure lines are exactly 45 degrees
-            assert_eq!((x-x2)*dx, (y-y2)*dy);
+        let id = *keys.entry(carrier_key(l)).or_insert_with(|| {
+            carrier_lines.push(Vec::new());
+            carrier_lines.len() - 1
+        });
+        carrier_lines[id].push(l);
+        carrier_of.push(id);
+    }
+    let mut direction = vec![(0, 0); carrier_lines.len()];
+    for (key, id) in &keys {
+        direction[*id] = key.0;
+    }
+    let mut overlaps = Overlaps::new();
+    let carriers: Vec<Carrier> = carrier_lines.iter().zip(direction).map(|(on, direction)| {
+        let mut carrier = Carrier { direction, runs: Vec::new() };
+        let mut events: Vec<(i64, isize)> = on.iter().flat_map(|l| {
+            let (s, e) = (carrier.position((l.start.x as i64, l.start.y as i64)), carrier.position((l.end.x as i64, l.end.y as i64)));
+            [(s.min(e), 1), (s.max(e) + 1, -1)]
+        }).collect();
+        events.sort_unstable();
+        let mut lines_here = 0;
+        for w in 0..events.len() {
+            lines_here += events[w].1;
+            if let Some(next) = events.get(w + 1).filter(|n| n.0 > events[w].0 && lines_here > 0) {
+                carrier.runs.push((events[w].0, next.0, lines_here as usize));
+                *overlaps.entry(lines_here as usize).or_default() += (next.0 - events[w].0) as u64;
+            }
         }
-        loop {
-            online.entry(Point{ x, y})
-                .and_modify(|taken| if *taken { double_points += 1; *taken = false; })
-                .or_insert(true);
-            if x == x2 && y == y2 {
-                break;
+        carrier
+    }).collect();
+    // the carriers through every point where lines on different carriers cross
+    let mut crossings: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    let mut order: Vec<usize> = (0..lines.len()).collect();
+    order.sort_unstable_by_key(|i| lines[*i].x_range().0);
+    let mut active: Vec<usize> = Vec::new();
+    for i in order {
+        let (minx, _) = lines[i].x_range();
+        active.retain(|a| lines[*a].x_range().1 >= minx);
+        for &j in &active {
+            let (ci, cj) = (carrier_of[i], carrier_of[j]);
+            if ci == cj {
+                continue;
             }
-            x += dx;
-            y += dy;
+            if let Some(p) = crossing(lines[i], lines[j]) {
+                let through = crossings.entry(p).or_default();
+                for c in [ci, cj] {
+                    if !through.contains(&c) {
+                        through.push(c);
+                    }
+                }
+            }
+        }
+        active.push(i);
+    }
+    // the runs counted a crossing once on every carrier, count it once with all its lines instead
+    for (p, through) in crossings {
+        let mut total = 0;
+        for c in through {
+            let here = carriers[c].lines_at(p);
+            let count = overlaps.get_mut(&here).expect("Crossings are on the lines");
+            *count -= 1;
+            total += here;
         }
+        *overlaps.entry(total).or_default() += 1;
     }
-    double_points
+    overlaps.retain(|_, count| *count > 0);
+    overlaps
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let lines = parse_input(input);
-    er.parse_done();
-    let mut hvlines: Vec<Line> = Vec::new();
-    let mut dlines: Vec<Line> = Vec::new();
-    for l in lines {
-        if l.start.x == l.end.x || l.start.y == l.end.y {
-            hvlines.push(l);
-        } else {
-            dlines.push(l);
-        }
+// the point of the grid where two lines that are not parallel cross, if any
+fn crossing(l1: &Line, l2: &Line) -> Option<(i64, i64)> {
+    let (((a1, b1), k1), ((a2, b2), k2)) = (carrier_key(l1), carrier_key(l2));
+    let (a1, b1, a2, b2) = (a1 as i128, b1 as i128, a2 as i128, b2 as i128);
+    // solve b1 x - a1 y = k1 and b2 x - a2 y = k2
+    let det = a1 * b2 - a2 * b1;
+    if det == 0 {
+        return None;
+    }
+    let (xn, yn) = (a1 * k2 - a2 * k1, b1 * k2 - b2 * k1);
+    if xn % det != 0 || yn % det != 0 {
+        return None;
     }
-    let mut online: HashMap<Point, bool> = HashMap::new();
-    let part1 = doublepoints(&hvlines, &mut online);
-    er.part1(part1, None);
-    let part2 = part1 + doublepoints(&dlines, &mut online);
-    er.part2(part2, None);
+    let p = ((xn / det) as i64, (yn / det) as i64);
+    let within = |l: &Line| {
+        let ((x0, x1), (y0, y1)) = (l.x_range(), l.y_range());
+        (x0..=x1).contains(&p.0) && (y0..=y1).contains(&p.1)
+    };
+    (within(l1) && within(l2)).then_some(p)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+// the overlaps, counted point by point when the lines cover a small enough area
+pub fn overlaps(lines: &[&Line]) -> Overlaps {
+    overlaps_dense(lines).unwrap_or_else(|| overlaps_sparse(lines))
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new(
-"0,9 -> 5,9
+// the overlap map as in the puzzle: the number of lines through every point, . for none and
+// # for more than 9. None when the area is larger than max_cells.
+pub fn render(lines: &[&Line], max_cells: i64) -> Option<String> {
+    let (count, _) = coverage(lines, max_cells)?;
+    Some(count.render(|c| match c {
+        0 => '.',
+        1..=9 => char::from_digit(*c, 10).unwrap(),
+        _ => '#',
+    }))
+}
+
+// how many lines cover every point, as a heatmap frame
+fn draw_heatmap(lines: &[&Line]) -> Grid<Rgb> {
+    let count = coverage(lines, DENSE_CELLS).map_or_else(|| Grid::new(0, 0, 0), |c| c.0);
+    let max = count.values().copied().max().unwrap_or(0);
+    count.map(|c| Rgb::heat(*c as usize, max as usize))
+}
+
+fn axis_aligned(lines: &[Line]) -> Vec<&Line> {
+    lines.iter().filter(|l| l.is_axis_aligned()).collect()
+}
+
+pub fn part1(lines: &[Line]) -> u64 {
+    at_least(&overlaps(&axis_aligned(lines)), 2)
+}
+
+pub fn part2(lines: &[Line]) -> u64 {
+    at_least(&overlaps(&lines.iter().collect::<Vec<_>>()), 2)
+}
+
+pub struct HydrothermalVenture;
+
+impl Solver for HydrothermalVenture {
+    type Input = Vec<Line>;
+    type Answer1 = u64;
+    type Answer2 = u64;
+
+    const DAY: u32 = 5;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Hydrothermal Venture";
+    const EXAMPLE: &'static str = "0,9 -> 5,9
 8,0 -> 0,8
 9,4 -> 3,4
 2,2 -> 2,1
@@ -114,16 +297,97 @@ mod tests {
 3,4 -> 1,4
 0,0 -> 8,8
 5,5 -> 8,2
-".as_bytes()
-        )
+";
+
+    fn parse(input: &str) -> Result<Vec<Line>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Vec<Line>) -> u64 {
+        part1(input)
+    }
+
+    fn part2(input: &Vec<Line>) -> u64 {
+        part2(input)
+    }
+
+    // the overlap heatmap of the horizontal and vertical lines, then of all lines
+    fn visualize(input: &Vec<Line>, viz: &mut Viz) {
+        viz.frame(|| draw_heatmap(&axis_aligned(input)));
+        viz.frame(|| draw_heatmap(&input.iter().collect::<Vec<_>>()));
+    }
+
+    // 500 horizontal, vertical or diagonal lines in a 1000x1000 field
+    fn generate(rng: &mut Rng) -> String {
+        let mut ret = String::new();
+        for _ in 0..500 {
+            let len = 10 + rng.below(500) as i64;
+            let x1 = len + rng.below((1000 - 2 * len) as u64) as i64;
+            let y1 = len + rng.below((1000 - 2 * len) as u64) as i64;
+            let dx = rng.below(3) as i64 - 1;
+            let dy = if dx == 0 { 1 } else { rng.below(3) as i64 - 1 };
+            ret += &format!("{x1},{y1} -> {},{}\n", x1 + dx * len, y1 + dy * len);
+        }
+        ret
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&HydrothermalVenture, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(HydrothermalVenture::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 5 - hydrothermal venture".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 5 - hydrothermal venture".to_string(), &HydrothermalVenture, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("5".to_string()));
         assert_eq!(er.answ()[1], Some("12".to_string()));
     }
 
+    #[test]
+    fn test_overlaps() {
+        let lines = parse(HydrothermalVenture::EXAMPLE).unwrap();
+        let all: Vec<&Line> = lines.iter().collect();
+        assert_eq!(overlaps(&all), Overlaps::from([(1, 27), (2, 10), (3, 2)]));
+        assert_eq!(overlaps_sparse(&all), overlaps(&all));
+        assert_eq!(render(&axis_aligned(&lines), 100).unwrap(),
+            ".......1..\n..1....1..\n..1....1..\n.......1..\n.112111211\n..........\n..........\n..........\n..........\n222111....");
+        assert_eq!(render(&all, 99), None);
+    }
+
+    #[test]
+    fn test_any_slope() {
+        // lines with any slope, single points, and lines on the same carrier going either way
+        let mut rng = Rng::new(5);
+        let mut input = "0,0 -> 6,3\n4,2 -> 2,1\n2,1 -> 2,1\n2,0 -> 2,4\n".to_string();
+        for _ in 0..300 {
+            let mut n = || rng.below(40) as i32 - 20;
+            input += &format!("{},{} -> {},{}\n", n(), n(), n(), n());
+        }
+        let lines = parse(&input).unwrap();
+        let all: Vec<&Line> = lines.iter().collect();
+        assert_eq!(overlaps_sparse(&all[..4]), Overlaps::from([(1, 6), (2, 1), (4, 1)]));
+        assert_eq!(overlaps_sparse(&all), overlaps_dense(&all).unwrap());
+        // far too big to count point by point
+        let huge = parse("-2147483648,0 -> 2147483647,0\n0,-2147483648 -> 0,2147483647\n-10,0 -> 10,0\n5,5 -> -5,-5\n").unwrap();
+        let huge: Vec<&Line> = huge.iter().collect();
+        assert_eq!(overlaps(&huge), Overlaps::from([(1, (1 << 32) - 21 + (1 << 32) - 1 + 10), (2, 20), (4, 1)]));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(parse("0,9 -> 5,9\n8,0 -> 0,x\n").err(),
+            Some(ParseError::new("Coordinates should be numbers").at_line(2).at_col(10).with_text("x")));
+        assert_eq!(parse("0,9 => 5,9\n").err(), Some(ParseError::new("Expected '->'").at_line(1).at_col(1).with_text("0,9 => 5,9")));
+    }
 }
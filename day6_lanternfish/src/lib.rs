@@ -1,66 +1,239 @@
-use exrunner::ExRunner;
-use std::io::{BufRead, read_to_string};
-use std::collections::HashMap;
-
-fn parse(input: impl BufRead) -> HashMap<u8, usize> {
-    let fishes: Vec<u8> = read_to_string(input).expect("Error reading input").trim()
-        .split(',').map(|i| i.parse::<u8>().expect("Input should be numbers")).collect();
-    let mut population: HashMap<u8, usize> = HashMap::new();
-    for f in fishes {
-        population.entry(f)
-            .and_modify(|count| *count += 1)
-            .or_insert(1);
-    }
-    population
+use exrunner::{Solver, ExRunner, ParseError, Text, Rng, Rgb, Screen};
+use std::ops::{AddAssign, Mul};
+use std::io::BufRead;
+use num_traits::{One, Zero};
+pub use num_bigint::BigUint;
+
+// parse the fish timers into a population count per timer value
+pub fn parse(input: &str) -> Result<Vec<u64>, ParseError> {
+    let msg = "Input should be numbers 0 to 8";
+    let mut population = vec![0; Model::AOC.timers()];
+    for t in Text::new(input).trim().split(",") {
+        match t.parse::<usize>(msg)? {
+            f if f < population.len() => population[f] += 1,
+            _ => return Err(t.err(msg)),
+        }
+    }
+    Ok(population)
 }
 
-fn process_one_day(fishpop: &mut HashMap<u8, usize>) {
-    // get 0-count population. Dereference to prevent a borrow of the hashmap
-    let popzero = *fishpop.get(&0).unwrap_or(&0);
-    for age in 1..=8 {
-        // every fish with energy "age" becomes "age - 1"
-        if let Some(popsize) = fishpop.get(&age) {
-            fishpop.insert(age - 1, *popsize);
-        } else {
-            fishpop.remove(&(age - 1));
+// The life cycle of lanternfish: a fish makes a new fish every `cycle` days, and a newborn fish
+// makes its first one after `newborn` days. A population is the number of fish per timer value,
+// the number of days until a fish makes a new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Model {
+    pub cycle: usize,
+    pub newborn: usize,
+}
+
+impl Model {
+    // the lanternfish of the puzzle
+    pub const AOC: Model = Model { cycle: 7, newborn: 9 };
+
+    // the number of timer values, 0 up to the timer of a newborn
+    pub fn timers(&self) -> usize {
+        assert!(self.cycle > 0 && self.newborn > 0, "Fish need at least a day to make a new one");
+        self.cycle.max(self.newborn)
+    }
+
+    fn check(&self, population: &[impl Sized]) {
+        assert_eq!(population.len(), self.timers(), "Population should have a count for every timer value");
+    }
+
+    // the population one day later
+    pub fn step<T>(&self, population: &[T]) -> Vec<T>
+        where T: Zero + Clone + for<'a> AddAssign<&'a T>
+    {
+        self.check(population);
+        let mut next: Vec<T> = population[1..].to_vec();
+        next.resize(self.timers(), T::zero());
+        next[self.cycle - 1] += &population[0];
+        next[self.newborn - 1] += &population[0];
+        next
+    }
+
+    // the population for every day starting with today. Never ends.
+    pub fn days<T>(self, population: Vec<T>) -> impl Iterator<Item = Vec<T>>
+        where T: Zero + Clone + for<'a> AddAssign<&'a T>
+    {
+        std::iter::successors(Some(population), move |prev| Some(self.step(prev)))
+    }
+
+    // transitions[to][from]: the fish with timer `to` that a fish with timer `from` turns into in a day
+    fn transitions(&self) -> Vec<Vec<u64>> {
+        let n = self.timers();
+        let mut transitions = vec![vec![0; n]; n];
+        for from in 1..n {
+            transitions[from - 1][from] = 1;
+        }
+        transitions[self.cycle - 1][0] += 1;
+        transitions[self.newborn - 1][0] += 1;
+        transitions
+    }
+
+    // the exact population after any number of days, by raising the matrix of transitions to the
+    // power days. The population grows exponentially, so use BigUint beyond a few hundred days.
+    pub fn population<T>(&self, population: &[T], days: u64) -> Vec<T>
+        where T: Zero + One + Clone + From<u64> + for<'a> AddAssign<&'a T>,
+              for<'a> &'a T: Mul<&'a T, Output = T>
+    {
+        self.check(population);
+        let mut matrix: Vec<Vec<T>> = self.transitions().into_iter().map(|row| row.into_iter().map(T::from).collect()).collect();
+        let mut population = population.to_vec();
+        let mut days = days;
+        while days > 0 {
+            if days & 1 == 1 {
+                population = matrix.iter().map(|row| dot(row, &population)).collect();
+            }
+            days >>= 1;
+            if days > 0 {
+                let columns: Vec<Vec<T>> = (0..matrix.len()).map(|c| matrix.iter().map(|row| row[c].clone()).collect()).collect();
+                matrix = matrix.iter().map(|row| columns.iter().map(|col| dot(row, col)).collect()).collect();
+            }
+        }
+        population
+    }
+
+    // the population modulo a number, for day counts too large for exact numbers, like 10^18
+    pub fn population_mod(&self, population: &[u64], days: u64, modulus: u32) -> Vec<u64> {
+        self.check(population);
+        assert!(modulus > 0, "Cannot take the population modulo 0");
+        let m = modulus as u64;
+        let dot_mod = |a: &[u64], b: &[u64]| (a.iter().zip(b).map(|(x, y)| (x * y) as u128).sum::<u128>() % m as u128) as u64;
+        let mut matrix = self.transitions();
+        let mut population: Vec<u64> = population.iter().map(|p| p % m).collect();
+        let mut days = days;
+        while days > 0 {
+            if days & 1 == 1 {
+                population = matrix.iter().map(|row| dot_mod(row, &population)).collect();
+            }
+            days >>= 1;
+            if days > 0 {
+                let columns: Vec<Vec<u64>> = (0..matrix.len()).map(|c| matrix.iter().map(|row| row[c]).collect()).collect();
+                matrix = matrix.iter().map(|row| columns.iter().map(|col| dot_mod(row, col)).collect()).collect();
+            }
         }
+        population
     }
-    // all population 0 fishes spanwed popuplation 8 fishes, who should now be gone.
-    fishpop.insert(8, popzero);
-    // additionally, the original population 0 fishes get added to the popuplation 6 fishes
-    fishpop.entry(6)
-        .and_modify(|pop6| *pop6 += popzero)
-        .or_insert(popzero);
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let mut fishpop = parse(input);
-    er.parse_done();
-    for _ in 0..80 {
-        process_one_day(&mut fishpop);
+fn dot<T>(a: &[T], b: &[T]) -> T
+    where T: Zero + for<'a> AddAssign<&'a T>,
+          for<'a> &'a T: Mul<&'a T, Output = T>
+{
+    let mut sum = T::zero();
+    for (x, y) in a.iter().zip(b).filter(|(x, y)| !x.is_zero() && !y.is_zero()) {
+        sum += &(x * y);
+    }
+    sum
+}
+
+// total population after the given number of days
+pub fn population_after(fishpop: &[u64], days: u64) -> u64 {
+    Model::AOC.population(fishpop, days).iter().sum()
+}
+
+pub fn part1(fishpop: &[u64]) -> u64 {
+    population_after(fishpop, 80)
+}
+
+pub fn part2(fishpop: &[u64]) -> u64 {
+    population_after(fishpop, 256)
+}
+
+pub struct Lanternfish;
+
+impl Solver for Lanternfish {
+    type Input = Vec<u64>;
+    type Answer1 = u64;
+    type Answer2 = u64;
+
+    const DAY: u32 = 6;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Lanternfish";
+    const EXAMPLE: &'static str = "3,4,3,1,2
+";
+
+    fn parse(input: &str) -> Result<Vec<u64>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Vec<u64>) -> u64 {
+        part1(input)
+    }
+
+    fn part2(input: &Vec<u64>) -> u64 {
+        part2(input)
+    }
+
+    // a histogram of the number of fish per timer value, for every day up to part 2
+    fn play(input: &Vec<u64>) -> Box<dyn Iterator<Item = Screen> + '_> {
+        Box::new(Model::AOC.days(input.clone()).take(257).enumerate().map(|(day, pop)| {
+            let total: u64 = pop.iter().sum();
+            let max = pop.iter().copied().max().unwrap_or(0).max(1);
+            let mut screen = Screen::new(format!("Day {day}: {total} lanternfish"), 80, pop.len());
+            for (timer, count) in pop.iter().enumerate() {
+                let bar = "#".repeat((count * 60 / max) as usize);
+                screen.text(0, timer, &format!("{timer}:"), Rgb::WHITE);
+                screen.text(3, timer, &bar, Rgb(0, 160, 255));
+                screen.text(4 + bar.len(), timer, &count.to_string(), Rgb::gray(160));
+            }
+            screen
+        }))
     }
-    er.part1(fishpop.values().map(|v| *v as u64).sum::<u64>(), None);
-    for _ in 80..256 {
-        process_one_day(&mut fishpop);
+
+    // 300 fish with timers 1 to 5
+    fn generate(rng: &mut Rng) -> String {
+        let fishes: Vec<String> = (0..300).map(|_| (1 + rng.below(5)).to_string()).collect();
+        fishes.join(",") + "\n"
     }
-    er.part2(fishpop.values().map(|v| *v as u64).sum::<u64>(), None);
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&Lanternfish, input, er)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use exrunner::ExRunner;
     use std::io::BufReader;
 
     fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new("3,4,3,1,2
-".as_bytes())
+        BufReader::new(Lanternfish::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 6 - lanternfish".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 6 - lanternfish".to_string(), &Lanternfish, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("5934".to_string()));
         assert_eq!(er.answ()[1], Some("26984457539".to_string()));
     }
+
+    #[test]
+    fn test_model() {
+        let fish = parse(Lanternfish::EXAMPLE).unwrap();
+        assert_eq!(population_after(&fish, 18), 26);
+        // the matrix agrees with stepping day by day, for other life cycles as well
+        let model = Model { cycle: 3, newborn: 5 };
+        let big: Vec<BigUint> = [0u32, 1, 0, 2, 0].into_iter().map(BigUint::from).collect();
+        assert_eq!(model.population(&big, 500), model.days(big.clone()).nth(500).unwrap());
+        let m = 1_000_000_007;
+        let exact = Model::AOC.population(&fish.iter().map(|f| BigUint::from(*f)).collect::<Vec<_>>(), 1000);
+        assert_eq!(Model::AOC.population_mod(&fish, 1000, m), exact.iter().map(|c| (c % m).try_into().unwrap()).collect::<Vec<u64>>());
+        assert_eq!(Model::AOC.population_mod(&fish, 1_000_000_000_000_000_000, m).len(), 9);
+    }
+
+    #[test]
+    #[should_panic(expected = "count for every timer value")]
+    fn test_wrong_length() {
+        Model { cycle: 3, newborn: 5 }.step(&parse(Lanternfish::EXAMPLE).unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "modulo 0")]
+    fn test_modulus_zero() {
+        Model::AOC.population_mod(&parse(Lanternfish::EXAMPLE).unwrap(), 10, 0);
+    }
 }
@@ -1,61 +1,258 @@
-use exrunner::ExRunner;
-use std::io::{BufRead, read_to_string};
-use std::collections::HashMap;
+use exrunner::{Solver, ExRunner, ParseError, Text, Rng};
+use std::io::BufRead;
 
-fn parse(input: impl BufRead) -> Vec<i32> {
-    read_to_string(input).expect("Error reading input").trim()
-        .split(',').map(|i| i.parse::<i32>().expect("Input should be numbers")).collect()
+pub fn parse(input: &str) -> Result<Vec<i64>, ParseError> {
+    if input.trim().is_empty() {
+        return Err(ParseError::new("Input should contain crabs").at_line(1));
+    }
+    Text::new(input).numbers(",", "Input should be numbers")
 }
 
-fn fuel_part2(target: i32, numbers: &Vec<i32>) -> i32 {
-    numbers.iter().map(|n| { let d = (target - *n).abs(); d * (d+1) / 2}).sum()
+// The fuel a crab uses to move a distance. Costs have to be convex and grow with the distance,
+// so that the total fuel has a single valley. Any closure from distance to fuel is a cost as well.
+pub trait Cost {
+    fn fuel(&self, distance: u64) -> u128;
+
+    // the positions the optimum is among, when that is known without searching
+    fn candidates(&self, _crabs: &[i64]) -> Option<Vec<i64>> {
+        None
+    }
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let mut numbers = parse(input);
-    er.parse_done();
-    numbers.sort_unstable();
-    // take the median == center number. If there is an even number of elements, then any of the middle 2 elements will do
-    let aim = numbers[(numbers.len() - 1) / 2];
-    er.part1(numbers.iter().map(|n| (aim - *n).abs()).sum::<i32>(), Some(&format!("Aim {aim}, energy level")));
-    // start with average, answer is probably around it.
-    let avg = (numbers.iter().sum::<i32>() + ((numbers.len() / 2) as i32)) / (numbers.len() as i32);
-    // start with a range around average
-    let mut start = avg - 1;
-    let mut end = avg + 1;
-    let mut fuel_at: HashMap<i32, i32> = HashMap::new();
-    loop {
-        for target in start..=end {
-            fuel_at.entry(target).or_insert_with(|| fuel_part2(target, &numbers));
+impl<F: Fn(u64) -> u128> Cost for F {
+    fn fuel(&self, distance: u64) -> u128 {
+        self(distance)
+    }
+}
+
+// a unit of fuel per step
+pub struct Linear;
+
+impl Cost for Linear {
+    fn fuel(&self, distance: u64) -> u128 {
+        distance as u128
+    }
+
+    // the median. With an even number of crabs anything between the middle two will do.
+    fn candidates(&self, crabs: &[i64]) -> Option<Vec<i64>> {
+        let mut crabs = crabs.to_vec();
+        crabs.sort_unstable();
+        Some(vec![crabs[(crabs.len() - 1) / 2]])
+    }
+}
+
+// every step costs one more than the one before
+pub struct Triangular;
+
+impl Cost for Triangular {
+    // d is below 2^64, so d * (d + 1) fits in a u128
+    fn fuel(&self, distance: u64) -> u128 {
+        let d = distance as u128;
+        d * (d + 1) / 2
+    }
+
+    // the optimum is within half a step of the mean
+    fn candidates(&self, crabs: &[i64]) -> Option<Vec<i64>> {
+        let (low, high) = mean(crabs);
+        Some((low.saturating_sub(1)..=high.saturating_add(1)).collect())
+    }
+}
+
+// the square of the distance
+pub struct Quadratic;
+
+impl Cost for Quadratic {
+    fn fuel(&self, distance: u64) -> u128 {
+        let d = distance as u128;
+        d * d
+    }
+
+    // the optimum is the mean
+    fn candidates(&self, crabs: &[i64]) -> Option<Vec<i64>> {
+        let (low, high) = mean(crabs);
+        Some(vec![low, high])
+    }
+}
+
+// the whole numbers around the mean
+fn mean(crabs: &[i64]) -> (i64, i64) {
+    let sum: i128 = crabs.iter().map(|c| *c as i128).sum();
+    let n = crabs.len() as i128;
+    (sum.div_euclid(n) as i64, (sum + n - 1).div_euclid(n) as i64)
+}
+
+// The total fuel for all crabs to move to a position, as the number of times it wrapped around
+// and what is left. Compares like the true total, also when that does not fit in a u128.
+fn total(crabs: &[i64], cost: &impl Cost, position: i64) -> (u64, u128) {
+    crabs.iter().fold((0, 0), |(wraps, sum), c| {
+        let (sum, wrapped) = sum.overflowing_add(cost.fuel(c.abs_diff(position)));
+        (wraps + wrapped as u64, sum)
+    })
+}
+
+// total fuel for all crabs to move to a position, None when it does not fit in a u128
+pub fn fuel_at(crabs: &[i64], cost: &impl Cost, position: i64) -> Option<u128> {
+    match total(crabs, cost, position) {
+        (0, fuel) => Some(fuel),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub position: i64,
+    pub fuel: u128,
+}
+
+// The position that takes the least fuel to align to. When several positions take the same
+// fuel, the lowest one is taken. None when there are no crabs, or the least fuel does not fit
+// in a u128.
+pub fn align(crabs: &[i64], cost: &impl Cost) -> Option<Alignment> {
+    let (min, max) = (*crabs.iter().min()?, *crabs.iter().max()?);
+    let best = |positions: &mut dyn Iterator<Item = i64>| {
+        let (total, position) = positions.map(|p| (total(crabs, cost, p), p)).min()?;
+        match total {
+            (0, fuel) => Some(Alignment { position, fuel }),
+            _ => None,
         }
-        // determine minimum and position that minimum occurs
-        let (min_at, min_fuel) = fuel_at.iter().reduce(|acc, e| if acc.1 < e.1 { acc } else { e } ).unwrap();
-        if *min_at == start {
-            start -= 2;
-        } else if *min_at == end {
-            end += 2;
+    };
+    if let Some(candidates) = cost.candidates(crabs) {
+        return best(&mut candidates.into_iter().map(|p| p.clamp(min, max)));
+    }
+    // the total is convex, so search for the first position where it stops going down
+    let (mut low, mut high) = (min, max);
+    while low < high {
+        let mid = low + (low.abs_diff(high) / 2) as i64;
+        if total(crabs, cost, mid) <= total(crabs, cost, mid + 1) {
+            high = mid;
         } else {
-            er.part2(*min_fuel, None);
-            break;
+            low = mid + 1;
         }
     }
+    best(&mut std::iter::once(low))
+}
+
+// the total fuel for every position from the leftmost to the rightmost crab, for plotting
+pub fn cost_curve(crabs: &[i64], cost: &impl Cost) -> Vec<(i64, Option<u128>)> {
+    match (crabs.iter().min(), crabs.iter().max()) {
+        (Some(&min), Some(&max)) => (min..=max).map(|p| (p, fuel_at(crabs, cost, p))).collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn part1(crabs: &[i64]) -> Option<u128> {
+    let aim = align(crabs, &Linear)?;
+    exrunner::info!("Aiming at the median, position {}", aim.position);
+    Some(aim.fuel)
+}
+
+pub fn part2(crabs: &[i64]) -> Option<u128> {
+    align(crabs, &Triangular).map(|a| a.fuel)
+}
+
+fn answer(fuel: Option<u128>) -> String {
+    fuel.map_or_else(|| "Total fuel does not fit in a u128".to_string(), |f| f.to_string())
+}
+
+pub struct TreacheryOfWhales;
+
+impl Solver for TreacheryOfWhales {
+    type Input = Vec<i64>;
+    // the fuel, or that it does not fit
+    type Answer1 = String;
+    type Answer2 = String;
+
+    const DAY: u32 = 7;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "The Treachery of Whales";
+    const LABEL1: Option<&'static str> = Some("Energy level at median");
+    const EXAMPLE: &'static str = "16,1,2,0,4,2,7,1,2,14
+";
+
+    fn parse(input: &str) -> Result<Vec<i64>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Vec<i64>) -> String {
+        answer(part1(input))
+    }
+
+    fn part2(input: &Vec<i64>) -> String {
+        answer(part2(input))
+    }
+
+    fn labels(input: &Vec<i64>) -> [Option<String>; 2] {
+        let aim = align(input, &Linear).map(|a| format!("Aim {}, energy level", a.position));
+        [aim.or(Self::LABEL1.map(String::from)), Self::LABEL2.map(String::from)]
+    }
+
+    // 1000 crabs spread out over 1500 positions
+    fn generate(rng: &mut Rng) -> String {
+        let crabs: Vec<String> = (0..1000).map(|_| rng.below(1500).to_string()).collect();
+        crabs.join(",") + "\n"
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&TreacheryOfWhales, input, er)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use exrunner::ExRunner;
     use std::io::BufReader;
 
     fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new("16,1,2,0,4,2,7,1,2,14
-".as_bytes())
+        BufReader::new(TreacheryOfWhales::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 7 - The Treachery of Whales".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 7 - The Treachery of Whales".to_string(), &TreacheryOfWhales, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("37".to_string()));
         assert_eq!(er.answ()[1], Some("168".to_string()));
+        assert!(er.raw().contains("Aim 2, energy level"));
+    }
+
+    #[test]
+    fn test_costs() {
+        let crabs = parse(TreacheryOfWhales::EXAMPLE).unwrap();
+        assert_eq!(align(&crabs, &Linear), Some(Alignment { position: 2, fuel: 37 }));
+        assert_eq!(align(&crabs, &Triangular), Some(Alignment { position: 5, fuel: 168 }));
+        // closures are searched, and agree with the closed forms
+        assert_eq!(align(&crabs, &|d: u64| Triangular.fuel(d)), align(&crabs, &Triangular));
+        let curve = cost_curve(&crabs, &Quadratic);
+        let lowest = curve.iter().min_by_key(|(p, f)| (*f, *p)).unwrap();
+        assert_eq!(align(&crabs, &Quadratic), Some(Alignment { position: lowest.0, fuel: lowest.1.unwrap() }));
+        assert_eq!(align(&crabs, &|d: u64| Quadratic.fuel(d)), align(&crabs, &Quadratic));
+        // ties go to the lowest position
+        assert_eq!(align(&[0, 10], &Linear), Some(Alignment { position: 0, fuel: 10 }));
+        assert_eq!(align(&[0, 10], &|d: u64| d as u128), Some(Alignment { position: 0, fuel: 10 }));
+        // far apart crabs do not overflow
+        let far = [i64::MIN, i64::MAX];
+        assert_eq!(align(&far, &Triangular).map(|a| a.fuel), align(&far, &|d: u64| Triangular.fuel(d)).map(|a| a.fuel));
+        assert_eq!(align(&[], &Linear), None);
+    }
+
+    #[test]
+    fn test_overflow() {
+        // every crab takes about 2^125 fuel to get to the middle
+        let far = [i64::MIN, i64::MAX].repeat(4);
+        assert_eq!(fuel_at(&far, &Triangular, 0), None);
+        assert_eq!(align(&far, &Triangular), None);
+        assert_eq!(align(&far, &|d: u64| Triangular.fuel(d)), None);
+        // a crab that is far away is avoided by the search, also when the total overflows elsewhere
+        let crabs = [0, 1, 2, i64::MAX];
+        assert_eq!(align(&crabs, &|d: u64| Triangular.fuel(d)).map(|a| a.position), align(&crabs, &Triangular).map(|a| a.position));
+        let input = far.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",") + "\n";
+        let er = ExRunner::run_solver("day 7 - The Treachery of Whales".to_string(), &TreacheryOfWhales, BufReader::new(input.as_bytes()));
+        assert_eq!(er.answ()[1], Some("Total fuel does not fit in a u128".to_string()));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("\n"), Err(ParseError::new("Input should contain crabs").at_line(1)));
     }
 }
@@ -1,155 +1,221 @@
-use exrunner::ExRunner;
+use exrunner::{Solver, ExRunner, ParseError, Text};
 use std::io::BufRead;
-use std::collections::HashMap;
-
-#[derive(Debug, PartialEq)]
-struct SSDisplay {
-    patterns: [u8; 10],
-    digits: [u8; 4],
-}
-
-fn segments_to_bitmap(segments: &str) -> u8 {
-    segments.chars().fold(0, |acc, c| acc | (1 << match c {
-        'a' ..= 'g' => (c  as u32) - ('a' as u32),
-        _ => panic!("Segments should only use a..g"),
-    }))
-}
-
-fn parse(input: impl BufRead) -> Vec<SSDisplay> {
-    // make sure the input contains of single lines containing both the patterns and the readout
-    let mut prevline: Option<String> = None;
-    input.lines().filter_map(|l| {
-        let line = l.expect("Error reading input");
-        if prevline.is_some() {
-            Some(prevline.take().unwrap() + &line)
-        } else if line.trim_end().ends_with('|') {
-            prevline = Some(line);
-            None
-        } else {
-            Some(line)
-        }
+
+// a display: the 10 unique patterns and the 4 digits shown, as bitmaps of segments a..g
+#[derive(Debug, Clone, PartialEq)]
+pub struct SSDisplay {
+    pub patterns: [u8; 10],
+    pub digits: [u8; 4],
+}
+
+// convert segment letters to a bitmap, None if there are letters outside a..g
+pub fn segments_to_bitmap(segments: &str) -> Option<u8> {
+    segments.chars().try_fold(0, |acc, c| match c {
+        'a' ..= 'g' => Some(acc | (1 << ((c as u32) - ('a' as u32)))),
+        _ => None,
     })
-    // now map each line to a SSDiplay
-    .map(|l: String| {
-        let (strpatterns, strdigits) = l.split_once('|').expect("Input lines should contain | separator");
-        let mut disp = SSDisplay{ patterns: [0; 10], digits: [0; 4] };
-        let mut enough = false;
-        for (i, bmap) in strpatterns.split_whitespace().map(|seg| segments_to_bitmap(seg)).enumerate() {
-            if i == disp.patterns.len() - 1 {
-                enough = true;
-            } else if i >= disp.patterns.len() {
-                panic!("Too many patterns");
-            }
-            disp.patterns[i] = bmap;
+}
+
+fn parse_bitmaps(input: Text, target: &mut [u8], what: &str) -> Result<(), ParseError> {
+    let mut enough = false;
+    for (i, seg) in input.split_whitespace().enumerate() {
+        if i == target.len() - 1 {
+            enough = true;
+        } else if i >= target.len() {
+            return Err(seg.err(format!("Too many {what}")));
+        }
+        target[i] = segments_to_bitmap(seg.as_str()).ok_or_else(|| seg.err("Segments should only use a..g"))?;
+    }
+    if !enough {
+        return Err(input.err(format!("Not enough {what}")));
+    }
+    Ok(())
+}
+
+// parse a display from a line, taking the digits from the next line when the line ends with the separator
+fn parse_display<'a>(line: Text<'a>, lines: &mut impl Iterator<Item = Text<'a>>) -> Result<SSDisplay, ParseError> {
+    let (strpatterns, mut strdigits) = line.pair("|")?;
+    if strdigits.is_empty() {
+        strdigits = lines.next().ok_or_else(|| line.err("Input should contain digits after |"))?;
+    }
+    let mut disp = SSDisplay{ patterns: [0; 10], digits: [0; 4] };
+    parse_bitmaps(strpatterns, &mut disp.patterns, "patterns")?;
+    parse_bitmaps(strdigits, &mut disp.digits, "digits")?;
+    Ok(disp)
+}
+
+pub fn parse(input: &str) -> Result<Vec<SSDisplay>, ParseError> {
+    let mut displays = Vec::new();
+    let mut lines = Text::new(input).lines();
+    while let Some(line) = lines.next() {
+        displays.push(parse_display(line, &mut lines)?);
+    }
+    Ok(displays)
+}
+
+// the segments a..g that are lit for each digit 0..9
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    pub digits: [u8; 10],
+}
+
+impl Font {
+    pub const STANDARD: Font = Font { digits: [0x77, 0x24, 0x5d, 0x6d, 0x2e, 0x6b, 0x7b, 0x25, 0x7f, 0x6f] };
+
+    // a font from the segment letters of each digit, None when they are not valid or not all different
+    pub fn new(digits: [&str; 10]) -> Option<Font> {
+        let mut bitmaps = [0; 10];
+        for (i, d) in digits.iter().enumerate() {
+            bitmaps[i] = segments_to_bitmap(d).filter(|b| !bitmaps[..i].contains(b))?;
+        }
+        Some(Font { digits: bitmaps })
+    }
+
+    // the standard font with 6 drawn without the top segment, 9 without the bottom one, or both
+    pub fn tails(six: bool, nine: bool) -> Font {
+        let mut font = Font::STANDARD;
+        if !six {
+            font.digits[6] &= !0x01;
         }
-        assert!(enough, "Not enough patterns");
-        enough = false;
-        for (i, bmap) in strdigits.split_whitespace().map(|seg| segments_to_bitmap(seg)).enumerate() {
-            if i == disp.digits.len() - 1 {
-                enough = true;
-            } else if i >= disp.digits.len() {
-                panic!("Too many digits");
+        if !nine {
+            font.digits[9] &= !0x40;
+        }
+        font
+    }
+
+    fn digit(&self, segments: u8) -> Option<u8> {
+        self.digits.iter().position(|d| *d == segments).map(|d| d as u8)
+    }
+}
+
+// which segment each wire a..g drives, 0 for segment a
+pub type Wiring = [u8; 7];
+
+// the segments lit by a pattern of wires
+fn rewire(wiring: &Wiring, pattern: u8) -> u8 {
+    (0..7).filter(|w| pattern & (1 << w) != 0).fold(0, |acc, w| acc | (1 << wiring[w]))
+}
+
+// All wirings that turn the patterns into the digits of the font. Wires are assigned one at a
+// time, dropping a partial wiring as soon as a pattern cannot become a digit anymore.
+pub fn wirings(patterns: &[u8; 10], font: &Font) -> Vec<Wiring> {
+    fn assign(wire: usize, wiring: &mut Wiring, used: u8, patterns: &[u8; 10], font: &Font, found: &mut Vec<Wiring>) {
+        if wire == 7 {
+            let mut digits: Vec<u8> = patterns.iter().map(|p| rewire(wiring, *p)).collect();
+            digits.sort_unstable();
+            let mut expected = font.digits;
+            expected.sort_unstable();
+            if digits == expected {
+                found.push(*wiring);
             }
-            disp.digits[i] = bmap;
+            return;
         }
-        assert!(enough, "Not enough digits");
-        disp
-    }).collect()
-}
-
-fn find_7seg_pattern(ssd: &SSDisplay) -> HashMap<u8, u8> {
-    // find out which digit is what. First the easy ones...
-    let mut seg2num: HashMap<u8, u8> = HashMap::new();
-    let mut opt_seg_cf = None;
-    let mut opt_seg_bd = None;
-    for pat in ssd.patterns {
-        match pat.count_ones() {
-            2 => { seg2num.insert(pat, 1); opt_seg_cf = Some(pat); },
-            3 => { seg2num.insert(pat, 7); },
-            4 => { seg2num.insert(pat, 4); opt_seg_bd = Some(pat); },
-            7 => { seg2num.insert(pat, 8); },
-            _ => (),
-        };
-    }
-    assert!(opt_seg_cf.is_some());
-    assert!(opt_seg_bd.is_some());
-    let seg_cf = opt_seg_cf.unwrap();
-    // opt_seg_bd really contains bdcf, so remote cf.
-    let seg_bd = opt_seg_bd.unwrap() & !seg_cf;
-    // scan the 6-segment patterns. If one of cf is missing, it's a 6 and the missing one is c.
-    // if one of bd is missing, it's a 0, and b is the one that's present in the pattern.
-    // the remaining 6-lit pattern is 9 and the missing one is e.
-    let mut opt_seg_b = None;
-    let mut opt_seg_c = None;
-    let mut opt_seg_e = None;
-    let mut opt_seg_f = None;
-    // just for reference (but not needed here):
-    // seg_a is simply pattern for 7 minus seg_cf
-    // seg_d is the one that's missing in 0 or seg_bd minus seg_b
-    // seg_g is the one that is left.
-    for pat in ssd.patterns.iter().filter(|p| p.count_ones() == 6) {
-        if *pat & seg_cf != seg_cf {
-            seg2num.insert(*pat, 6);
-            opt_seg_c = Some(seg_cf & !*pat);
-            opt_seg_f = Some(seg_cf & *pat);
-        } else if *pat & seg_bd != seg_bd {
-            seg2num.insert(*pat, 0);
-            opt_seg_b = Some(seg_bd & *pat);
-        } else {
-            seg2num.insert(*pat, 9);
-            opt_seg_e = Some(0x7f & !*pat);
+        for segment in (0..7).filter(|s| used & (1 << s) == 0) {
+            wiring[wire] = segment;
+            let assigned = (1u8 << (wire + 1)) - 1;
+            let possible = patterns.iter().all(|p| {
+                let on = rewire(wiring, p & assigned);
+                let off = rewire(wiring, !p & assigned);
+                font.digits.iter().any(|d| d.count_ones() == p.count_ones() && d & on == on && d & off == 0)
+            });
+            if possible {
+                assign(wire + 1, wiring, used | (1 << segment), patterns, font, found);
+            }
         }
     }
-    assert!(opt_seg_b.is_some());
-    assert!(opt_seg_c.is_some());
-    assert!(opt_seg_e.is_some());
-    assert!(opt_seg_f.is_some());
-    // now look at the 5-segment patterns. If bf is missing, it's a 2,
-    // if be is missing, it's a 3, if ce is missing it's a 5.
-    for pat in ssd.patterns.iter().filter(|p| p.count_ones() == 5) {
-        if *pat & (opt_seg_b.unwrap() | opt_seg_f.unwrap()) == 0 {
-            seg2num.insert(*pat, 2);
-        } else if *pat & (opt_seg_b.unwrap() | opt_seg_e.unwrap()) == 0 {
-            seg2num.insert(*pat, 3);
-        } else if *pat & (opt_seg_c.unwrap() | opt_seg_e.unwrap()) == 0 {
-            seg2num.insert(*pat, 5);
-        } else {
-            panic!("Invalid 7-segment patterns, unexpected 5-seg pattern");
+    let mut found = Vec::new();
+    assign(0, &mut [0; 7], 0, patterns, font, &mut found);
+    found
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    // no wiring shows the patterns as the digits of the font
+    Impossible,
+    // more than one wiring does
+    Ambiguous(usize),
+    // a digit shown is not one of the patterns
+    UnknownDigit(u8),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DecodeError::Impossible => write!(f, "No wiring matches the patterns"),
+            DecodeError::Ambiguous(n) => write!(f, "Patterns match {n} different wirings"),
+            DecodeError::UnknownDigit(d) => write!(f, "Digit {} is not one of the patterns", bitmap_to_segments(*d)),
         }
     }
-    // we should have all 10 numbers now.
-    assert_eq!(seg2num.len(), 10);
-    seg2num
 }
 
-fn decode_digits(ssd: &SSDisplay) -> u32 {
-    let decode = find_7seg_pattern(ssd);
-    let mut answ: u32 = 0;
-    for dpat in ssd.digits {
-        answ = answ * 10 + *decode.get(&dpat).expect("Unknown pattern in digits") as u32;
+impl std::error::Error for DecodeError {}
+
+// the segment letters of a bitmap
+pub fn bitmap_to_segments(bitmap: u8) -> String {
+    ('a'..='g').enumerate().filter(|(i, _)| bitmap & (1 << i) != 0).map(|(_, c)| c).collect()
+}
+
+// a display with its wiring worked out
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoded {
+    pub display: SSDisplay,
+    pub wiring: Wiring,
+    // the digit of each pattern
+    pub patterns: [u8; 10],
+    // the digits shown, and the number they make
+    pub digits: [u8; 4],
+    pub value: u32,
+}
+
+pub fn decode(display: SSDisplay, font: &Font) -> Result<Decoded, DecodeError> {
+    let wiring = match wirings(&display.patterns, font)[..] {
+        [] => return Err(DecodeError::Impossible),
+        [wiring] => wiring,
+        ref all => return Err(DecodeError::Ambiguous(all.len())),
+    };
+    let digit = |p: u8| font.digit(rewire(&wiring, p)).expect("Wiring maps patterns to digits");
+    let patterns = display.patterns.map(digit);
+    let mut digits = [0; 4];
+    for (i, d) in display.digits.iter().enumerate() {
+        if !display.patterns.contains(d) {
+            return Err(DecodeError::UnknownDigit(*d));
+        }
+        digits[i] = digit(*d);
     }
-    answ
+    let value = digits.iter().fold(0, |acc, d| acc * 10 + *d as u32);
+    Ok(Decoded { display, wiring, patterns, digits, value })
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let inlist = parse(input);
-    er.parse_done();
-    // do part1, count number of digits that are 1, 4, 7, or 8 (2, 4, 3, 7 segmenets lit)
-    let answ1 = inlist.iter().map(|ssd| ssd.digits.iter()).flatten()
-        .filter(|d| match d.count_ones() { 2..=4 => true, 7 => true, _ => false})
-        .count();
-    er.part1(answ1, None);
-    let answ2: u32 = inlist.iter().map(|ssd| decode_digits(ssd)).sum();
-    er.part2(answ2, None);
+pub fn decode_digits(ssd: &SSDisplay) -> Result<u32, DecodeError> {
+    decode(ssd.clone(), &Font::STANDARD).map(|d| d.value)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::BufReader;
+// count the digits that are 1, 4, 7, or 8: the only digits with 2, 4, 3 or 7 segments
+pub fn part1(displays: &[SSDisplay]) -> usize {
+    displays.iter().flat_map(|ssd| ssd.digits.iter())
+        .filter(|d| matches!(d.count_ones(), 2..=4 | 7))
+        .count()
+}
 
-    fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new("be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe
+// the sum of the numbers shown, or the index of the first display that cannot be decoded
+pub fn part2(displays: &[SSDisplay]) -> Result<u32, (usize, DecodeError)> {
+    displays.iter().enumerate()
+        .map(|(i, ssd)| decode_digits(ssd).map_err(|e| (i, e)))
+        .sum()
+}
+
+pub struct SevenSegment;
+
+impl Solver for SevenSegment {
+    type Input = Vec<SSDisplay>;
+    type Answer1 = usize;
+    // the sum, or why a display cannot be decoded
+    type Answer2 = String;
+
+    const DAY: u32 = 8;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Seven Segment Search";
+    const EXAMPLE: &'static str = "be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe
 edbfga begcd cbg gc gcadebf fbgde acbgfd abcde gfcbed gfec | fcgedb cgb dgebacf gc
 fgaebd cg bdaec gdafb agbcfd gdcbef bgcad gfac gcb cdgabef |
 cg cg fdcagb cbg
@@ -167,48 +233,105 @@ egadfb cdbfeg cegd fecab cgb gbdefca cg fgcdab egfdb bfceg |
 gbdfcae bgc cg cgb
 gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc |
 fgae cfgab fg bagce
-".as_bytes())
+";
+
+    fn parse(input: &str) -> Result<Vec<SSDisplay>, ParseError> {
+        parse(input)
     }
 
-    fn simple_test_input() -> BufReader<&'static [u8]> {
-        BufReader::new("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf".as_bytes())
+    fn part1(input: &Vec<SSDisplay>) -> usize {
+        part1(input)
+    }
+
+    fn part2(input: &Vec<SSDisplay>) -> String {
+        part2(input).map_or_else(|(i, e)| format!("Cannot decode display {}: {e}", i + 1), |sum| sum.to_string())
+    }
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&SevenSegment, input, er)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exrunner::ExRunner;
+    use std::io::BufReader;
+
+    fn test_input() -> BufReader<&'static [u8]> {
+        BufReader::new(SevenSegment::EXAMPLE.as_bytes())
+    }
+
+    fn simple_test_input() -> &'static str {
+        "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf"
     }
 
     #[test]
     fn test_find_7seg() {
-        let testin = parse(simple_test_input());
-        let s7 = find_7seg_pattern(&testin[0]);
-        assert_eq!(s7.get(&0x3), Some(&1)); // ab
-        assert_eq!(s7.get(&0x3F), Some(&9)); // cefabd
-        assert_eq!(s7.get(&0x3E), Some(&5)); // cdfbe
-        assert_eq!(s7.get(&0x2F), Some(&3)); // fbcad
+        let testin = parse(simple_test_input()).unwrap();
+        let d = decode(testin[0].clone(), &Font::STANDARD).unwrap();
+        let digit = |p: u8| d.patterns[d.display.patterns.iter().position(|x| *x == p).unwrap()];
+        assert_eq!(digit(0x3), 1); // ab
+        assert_eq!(digit(0x3F), 9); // cefabd
+        assert_eq!(digit(0x3E), 5); // cdfbe
+        assert_eq!(digit(0x2F), 3); // fbcad
+        // wire d drives the top segment
+        assert_eq!(d.wiring, [2, 5, 6, 0, 1, 3, 4]);
     }
 
     #[test]
     fn test_decode_single() {
-        let testin = parse(simple_test_input());
-        assert_eq!(decode_digits(&testin[0]), 5353);
+        let testin = parse(simple_test_input()).unwrap();
+        assert_eq!(decode_digits(&testin[0]), Ok(5353));
+    }
+
+    #[test]
+    fn test_fonts() {
+        let ssd = parse(simple_test_input()).unwrap().remove(0);
+        assert_eq!(decode(ssd.clone(), &Font::tails(false, true)).err(), Some(DecodeError::Impossible));
+        // the same display drawn with a 6 without its tail
+        let font = Font::tails(false, false);
+        let mut tailless = ssd.clone();
+        tailless.patterns[6] &= !0x08;
+        tailless.patterns[5] &= !0x04;
+        let d = decode(tailless, &font).unwrap();
+        assert_eq!(d.patterns[5..7], [9, 6]);
+        assert_eq!(d.value, 5353);
+        assert!(Font::new(["abc", "abc", "", "", "", "", "", "", "", ""]).is_none());
+        // in this font b and e are always lit together, as are a, c, d and f
+        let font = Font::new(["a", "c", "d", "f", "g", "be", "abe", "bce", "bde", "bef"]).unwrap();
+        let ssd = parse("a c d f g be abe bce bde bef | g g g g").unwrap().remove(0);
+        assert_eq!(decode(ssd, &font).err(), Some(DecodeError::Ambiguous(48)));
+        let displays = parse(&format!("{}\nab ab ab ab ab ab ab ab ab ab | ab ab ab ab\n", simple_test_input())).unwrap();
+        assert_eq!(part2(&displays), Err((1, DecodeError::Impossible)));
     }
 
     #[test]
     fn test_segments_to_bitmap() {
-        assert_eq!(segments_to_bitmap("ab"), 0x3);
-        assert_eq!(segments_to_bitmap("g"), 0x40);
+        assert_eq!(segments_to_bitmap("ab"), Some(0x3));
+        assert_eq!(segments_to_bitmap("g"), Some(0x40));
+        assert_eq!(segments_to_bitmap("ah"), None);
     }
 
     #[test]
     fn test_parse() {
         let answer = vec![SSDisplay{ patterns: [ 0x12, 0x7F, 0x7E, 0x7D, 0x56, 0x7C, 0x7B, 0x3E, 0x2F, 0x1A ], digits: [0x7F, 0x3E, 0x7E, 0x56]}];
-        assert_eq!(parse(BufReader::new("be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe".as_bytes())), answer);
-        assert_eq!(parse(BufReader::new("be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb |
-    fdgacbe cefdb cefbgd gcbe".as_bytes())), answer);
+        assert_eq!(parse("be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe").as_ref(), Ok(&answer));
+        assert_eq!(parse("be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb |
+    fdgacbe cefdb cefbgd gcbe"), Ok(answer));
+        assert_eq!(parse("be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd | fdgacbe cefdb cefbgd gcbe"),
+            Err(ParseError::new("Not enough patterns").at_line(1).at_col(1).with_text("be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd")));
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 8 - Seven Segment Search".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 8 - Seven Segment Search".to_string(), &SevenSegment, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("26".to_string()));
         assert_eq!(er.answ()[1], Some("61229".to_string()));
+        // a display that cannot be decoded is reported in the answer
+        let input = format!("{}\nab ab ab ab ab ab ab ab ab ab | ab ab ab ab\n", simple_test_input());
+        let er = ExRunner::run_solver("day 8 - Seven Segment Search".to_string(), &SevenSegment, BufReader::new(input.as_bytes()));
+        assert_eq!(er.answ()[1], Some("Cannot decode display 2: No wiring matches the patterns".to_string()));
     }
 }
@@ -1,85 +1,119 @@
-use exrunner::ExRunner;
-use std::{io::BufRead, collections::HashMap};
+use exrunner::{Solver, ExRunner, ParseError, Rng, Grid, Rgb, Viz};
+use std::io::BufRead;
 
-fn parse(input: impl BufRead) -> Vec<Vec<u8>> {
-    input.lines().map(|l| l.expect("Error reading input").as_bytes().iter().map(|b| *b - ('0' as u8)).collect()).collect()
+pub fn parse(input: &str) -> Result<Grid<u8>, ParseError> {
+    Grid::parse_digits(input)
 }
 
-pub fn solve(input: impl BufRead, er: &mut ExRunner) {
-    let heightmap = parse(input);
-    er.parse_done();
-    let mut total_risk: u32 = 0;
-    let mut basins: Vec<usize> = Vec::new();
-    for y in 0 .. heightmap.len() {
-        for x in 0 .. heightmap[y].len() {
-            let h = heightmap[y][x];
-            if y > 0 && h >= heightmap[y-1][x] {
-                continue;
-            } else if x > 0 && h >= heightmap[y][x-1] {
-                continue;
-            } else if y < heightmap.len() - 1 && h >= heightmap[y+1][x] {
-                continue;
-            } else if x < heightmap[y].len() - 1 && h >= heightmap[y][x+1] {
-                continue;
-            }
-            total_risk += (h + 1) as u32;
-            let bsize = get_basin_size(&heightmap, x, y);
-            er.debugln(&format!("Low point at {x},{y} = {h}, basin size={bsize}"));
-            basins.push(bsize);
-        }
-    }
-    er.part1(total_risk, None);
+// find all the low points in the height map, as (x, y) coordinates
+pub fn low_points(heightmap: &Grid<u8>) -> Vec<(usize, usize)> {
+    heightmap.iter()
+        .filter(|((x, y), h)| heightmap.neighbours4(*x, *y).all(|n| **h < heightmap[n]))
+        .map(|(p, _)| p)
+        .collect()
+}
+
+pub fn part1(heightmap: &Grid<u8>) -> u32 {
+    low_points(heightmap).into_iter().map(|p| (heightmap[p] + 1) as u32).sum()
+}
+
+pub fn part2(heightmap: &Grid<u8>) -> usize {
+    let mut basins: Vec<usize> = low_points(heightmap).into_iter()
+        .map(|(x, y)| get_basin_size(heightmap, x, y))
+        .collect();
     basins.sort();
-    er.part2(basins.into_iter().rev().take(3).fold(1, |acc, e| acc * e), None);
+    basins.into_iter().rev().take(3).product()
 }
 
-fn get_basin_size(hmap: &Vec<Vec<u8>>, x: usize, y: usize) -> usize {
-    let mut basinpoints: HashMap<(i32, i32), ()> = HashMap::new();
-    let mut consider: Vec<(i32, i32)> = Vec::new();
-    consider.push((x as i32, y as i32));
-    basinpoints.insert((x as i32, y as i32), ());
-    while !consider.is_empty() {
-        let (xc, yc) = consider.pop().unwrap();
-        let refh = hmap[yc as usize][xc as usize];
-        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
-            if yc + dy < 0 || yc + dy >= hmap.len() as i32 || xc + dx < 0 || xc + dx >= hmap[(yc+dy) as usize].len() as i32 {
-                // out of field
-                continue;
+pub struct SmokeBasin;
+
+impl Solver for SmokeBasin {
+    type Input = Grid<u8>;
+    type Answer1 = u32;
+    type Answer2 = usize;
+
+    const DAY: u32 = 9;
+    const YEAR: u16 = 2021;
+    const TITLE: &'static str = "Smoke Basin";
+    const EXAMPLE: &'static str = "2199943210
+3987894921
+9856789892
+8767896789
+9899965678
+";
+
+    fn parse(input: &str) -> Result<Grid<u8>, ParseError> {
+        parse(input)
+    }
+
+    fn part1(input: &Grid<u8>) -> u32 {
+        part1(input)
+    }
+
+    fn part2(input: &Grid<u8>) -> usize {
+        part2(input)
+    }
+
+    // the height map in shades of gray, then a frame for every basin that gets filled in
+    fn visualize(input: &Grid<u8>, viz: &mut Viz) {
+        let mut frame = input.map(|h| Rgb::gray(h * 25));
+        viz.frame(|| frame.clone());
+        for (i, (x, y)) in low_points(input).into_iter().enumerate() {
+            let colour = [Rgb(0, 120, 255), Rgb(0, 200, 120), Rgb(120, 80, 255)][i % 3];
+            for p in basin(input, x, y) {
+                frame[p] = colour;
             }
-            let (xn, yn) = ((xc+dx) as usize, (yc+dy) as usize);
-            if hmap[yn][xn] == 9 || hmap[yn][xn] < refh {
-                // 9 isn't part of basin, and basin cannot go down
-                continue;
+            viz.frame(|| frame.clone());
+        }
+    }
+
+    // a 100x100 height map, with about 1 in 8 locations at height 9
+    fn generate(rng: &mut Rng) -> String {
+        let mut ret = String::new();
+        for _ in 0..100 {
+            for _ in 0..100 {
+                let h = if rng.below(8) == 0 { 9 } else { rng.below(9) };
+                ret += &h.to_string();
             }
-            let newp = (xn as i32, yn as i32);
-            basinpoints.entry(newp).or_insert_with(|| {consider.push(newp); ()} );
+            ret += "\n";
         }
+        ret
     }
-    basinpoints.len()
+}
+
+pub fn solve(input: impl BufRead, er: &mut ExRunner) {
+    exrunner::run_solver(&SmokeBasin, input, er)
+}
+
+// all positions of the basin around a low point
+pub fn basin(hmap: &Grid<u8>, x: usize, y: usize) -> Vec<(usize, usize)> {
+    // 9 isn't part of basin, and basin cannot go down
+    hmap.flood_fill((x, y), |from, to| *to != 9 && *to >= *from)
+}
+
+pub fn get_basin_size(hmap: &Grid<u8>, x: usize, y: usize) -> usize {
+    basin(hmap, x, y).len()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use exrunner::ExRunner;
     use std::io::BufReader;
 
     fn test_input() -> BufReader<&'static [u8]> {
-        BufReader::new("2199943210
-3987894921
-9856789892
-8767896789
-9899965678
-".as_bytes())
+        BufReader::new(SmokeBasin::EXAMPLE.as_bytes())
     }
 
     #[test]
     fn test_parse() {
-        assert_eq!(parse(BufReader::new("123\n456\n".as_bytes())), vec![vec![1,2,3], vec![4,5,6]]);
+        assert_eq!(parse("123\n456\n"), Grid::from_rows(vec![vec![1,2,3], vec![4,5,6]]));
+        assert_eq!(parse("123\n4a6\n"), Err(ParseError::new("Input should be digits").at_line(2).at_col(2).with_text("a")));
     }
 
     #[test]
     fn test_solve() {
-        let er = ExRunner::run("day 9 - smoke basin".to_string(), solve, test_input());
+        let er = ExRunner::run_solver("day 9 - smoke basin".to_string(), &SmokeBasin, test_input());
         er.print_raw();
         assert_eq!(er.answ()[0], Some("15".to_string()));
         assert_eq!(er.answ()[1], Some("1134".to_string()));
@@ -0,0 +1,212 @@
+use std::ops::{Index, IndexMut};
+use crate::{ParseError, Text};
+
+// offsets of the 4 direct neighbours, and of all 8 neighbours including diagonals
+const DIRS4: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const DIRS8: [(isize, isize); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+
+// Grid is a rectangular 2D field of cells, indexed by (x, y) with (0, 0) in the upper left corner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Grid<T>
+        where T: Clone
+    {
+        Grid { width, height, cells: vec![fill; width * height] }
+    }
+
+    // build a grid from rows, which should all be of the same length
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Grid<T>, ParseError> {
+        let height = rows.len();
+        let width = rows.first().map(|r| r.len()).unwrap_or(0);
+        if let Some(y) = rows.iter().position(|r| r.len() != width) {
+            return Err(ParseError::new("Grid rows should all be of the same length").at_line(y + 1));
+        }
+        Ok(Grid { width, height, cells: rows.into_iter().flatten().collect() })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if x < self.width && y < self.height {
+            Some(&self.cells[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        if x < self.width && y < self.height {
+            Some(&mut self.cells[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    // all positions, row by row
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    // all cells with their position, row by row
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.positions().zip(self.cells.iter())
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.cells.iter_mut()
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // chunks() does not accept 0, an empty grid has no rows anyway
+        self.cells.chunks(self.width.max(1))
+    }
+
+    fn neighbours<'a>(&self, x: usize, y: usize, dirs: &'a [(isize, isize)]) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let (width, height) = (self.width, self.height);
+        dirs.iter().filter_map(move |(dx, dy)| {
+            let nx = x.checked_add_signed(*dx).filter(|nx| *nx < width)?;
+            let ny = y.checked_add_signed(*dy).filter(|ny| *ny < height)?;
+            Some((nx, ny))
+        })
+    }
+
+    // the horizontal and vertical neighbours of a position that are inside the grid
+    pub fn neighbours4(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.neighbours(x, y, &DIRS4)
+    }
+
+    // all neighbours of a position that are inside the grid, including diagonals
+    pub fn neighbours8(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.neighbours(x, y, &DIRS8)
+    }
+
+    pub fn map<U>(&self, f: impl Fn(&T) -> U) -> Grid<U> {
+        Grid { width: self.width, height: self.height, cells: self.cells.iter().map(f).collect() }
+    }
+
+    // repeat the grid tiles_x times horizontally and tiles_y times vertically. The function gets
+    // each original cell and the tile column and row, and returns the value in the new grid.
+    pub fn tile(&self, tiles_x: usize, tiles_y: usize, f: impl Fn(&T, usize, usize) -> T) -> Grid<T> {
+        let width = self.width * tiles_x;
+        let height = self.height * tiles_y;
+        let mut cells = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                cells.push(f(&self[(x % self.width, y % self.height)], x / self.width, y / self.height));
+            }
+        }
+        Grid { width, height, cells }
+    }
+
+    // find all positions reachable from start, using 4-neighbour steps. connected(from, to) decides
+    // if a step from one cell to the next is possible. The start position is always included.
+    pub fn flood_fill(&self, start: (usize, usize), mut connected: impl FnMut(&T, &T) -> bool) -> Vec<(usize, usize)> {
+        let mut seen = vec![false; self.cells.len()];
+        let mut filled = vec![start];
+        let mut consider = vec![start];
+        seen[start.1 * self.width + start.0] = true;
+        while let Some((x, y)) = consider.pop() {
+            for (nx, ny) in self.neighbours4(x, y) {
+                let ni = ny * self.width + nx;
+                if !seen[ni] && connected(&self[(x, y)], &self.cells[ni]) {
+                    seen[ni] = true;
+                    filled.push((nx, ny));
+                    consider.push((nx, ny));
+                }
+            }
+        }
+        filled
+    }
+
+    // render the grid as text, one line per row, using f to draw each cell
+    pub fn render(&self, f: impl Fn(&T) -> char) -> String {
+        self.rows().map(|r| r.iter().map(&f).collect::<String>()).collect::<Vec<String>>().join("\n")
+    }
+}
+
+impl Grid<u8> {
+    // parse a grid of single digits, as used by lots of puzzles
+    pub fn parse_digits(input: &str) -> Result<Grid<u8>, ParseError> {
+        Text::new(input).grid(|c| c.to_digit(10).map(|d| d as u8), "Input should be digits")
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        assert!(x < self.width && y < self.height, "Position {x},{y} outside of grid");
+        &self.cells[y * self.width + x]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        assert!(x < self.width && y < self.height, "Position {x},{y} outside of grid");
+        &mut self.cells[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_digits() {
+        let g = Grid::parse_digits("123\n456\n").unwrap();
+        assert_eq!((g.width(), g.height()), (3, 2));
+        assert_eq!(g[(2, 1)], 6);
+        assert_eq!(g.get(3, 0), None);
+        assert_eq!(Grid::parse_digits("12\n4x\n"), Err(ParseError::new("Input should be digits").at_line(2).at_col(2).with_text("x")));
+        assert_eq!(Grid::parse_digits("12\n456\n"), Err(ParseError::new("Grid rows should all be of the same length").at_line(2).at_col(3)));
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let g = Grid::new(3, 3, 0);
+        assert_eq!(g.neighbours4(0, 0).collect::<Vec<_>>(), vec![(1, 0), (0, 1)]);
+        assert_eq!(g.neighbours4(1, 1).count(), 4);
+        assert_eq!(g.neighbours8(0, 2).collect::<Vec<_>>(), vec![(0, 1), (1, 1), (1, 2)]);
+        assert_eq!(g.neighbours8(1, 1).count(), 8);
+    }
+
+    #[test]
+    fn test_tile() {
+        let g = Grid::parse_digits("18\n").unwrap();
+        let big = g.tile(2, 2, |v, tx, ty| (v + tx as u8 + ty as u8 - 1) % 9 + 1);
+        assert_eq!(big.render(|v| (b'0' + v) as char), "1829\n2931");
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let g = Grid::parse_digits("119\n191\n111\n").unwrap();
+        let mut filled = g.flood_fill((0, 0), |_, to| *to != 9);
+        filled.sort();
+        assert_eq!(filled, vec![(0, 0), (0, 1), (0, 2), (1, 0), (1, 2), (2, 1), (2, 2)]);
+    }
+}
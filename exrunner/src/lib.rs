@@ -1,6 +1,19 @@
 use std::time::{Duration, Instant};
 use std::io::{self, BufRead, Write};
-use std::fmt::Display;
+use std::fmt::{self, Display};
+use std::error::Error;
+use std::any::Any;
+
+pub mod grid;
+pub mod ocr;
+pub mod parse;
+pub mod trace;
+pub mod viz;
+
+pub use grid::Grid;
+pub use parse::Text;
+pub use trace::{Level, Phase, Record};
+pub use viz::{Frame, Rgb, Viz, Screen};
 
 // ExRunner runs an exercise and keeps status
 pub struct ExRunner<'a, 'b> {
@@ -11,18 +24,162 @@ pub struct ExRunner<'a, 'b> {
     parsetime: Option<Duration>,
     runtime: [Option<Duration>; 2],
     totaltime: Option<Duration>,
+    error: Option<ParseError>,
     debug: Option<Box<dyn Write + 'b>>,
+    log: Vec<Record>,
+    viz: Option<Viz>,
+}
+
+// Solver is implemented by every puzzle. The input is parsed once, and both parts are
+// calculated from the parsed input.
+pub trait Solver {
+    type Input: 'static;
+    type Answer1: Display + 'static;
+    type Answer2: Display + 'static;
+
+    const DAY: u32;
+    const YEAR: u16;
+    const TITLE: &'static str;
+    // labels to show with the answers, defaults to "part1" and "part2"
+    const LABEL1: Option<&'static str> = None;
+    const LABEL2: Option<&'static str> = None;
+    // the example input from the puzzle description
+    const EXAMPLE: &'static str;
+
+    fn parse(input: &str) -> Result<Self::Input, ParseError>;
+    fn part1(input: &Self::Input) -> Self::Answer1;
+    fn part2(input: &Self::Input) -> Self::Answer2;
+
+    // the labels for this input, to show something found while solving. Defaults to LABEL1 and LABEL2.
+    fn labels(_input: &Self::Input) -> [Option<String>; 2] {
+        [Self::LABEL1.map(String::from), Self::LABEL2.map(String::from)]
+    }
+
+    // generate an input of realistic size, used when benchmarking without the real input.
+    // Defaults to the example input.
+    fn generate(_rng: &mut Rng) -> String {
+        Self::EXAMPLE.to_string()
+    }
+
+    // draw frames of the solution, for `aoc viz`. Only called when visualising, so it does not
+    // slow down normal runs. Defaults to no frames.
+    fn visualize(_input: &Self::Input, _viz: &mut Viz) {}
+
+    // the states of the simulation one step at a time, for `aoc play`. Defaults to no steps.
+    fn play(_input: &Self::Input) -> Box<dyn Iterator<Item = Screen> + '_> {
+        Box::new(std::iter::empty())
+    }
+}
+
+// DynSolver is the type-erased version of Solver, so different puzzles can be kept in one list.
+// It is implemented for every Solver. Solvers are shared between threads, so they can be run with
+// a timeout.
+pub trait DynSolver: Sync {
+    fn day(&self) -> u32;
+    fn year(&self) -> u16;
+    fn title(&self) -> &'static str;
+    fn labels(&self, input: &dyn Any) -> [Option<String>; 2];
+    fn example(&self) -> &'static str;
+    fn generate_any(&self, rng: &mut Rng) -> String;
+    fn parse_any(&self, input: &str) -> Result<Box<dyn Any>, ParseError>;
+    fn part1_any(&self, input: &dyn Any) -> Box<dyn Display>;
+    fn part2_any(&self, input: &dyn Any) -> Box<dyn Display>;
+    fn visualize_any(&self, input: &dyn Any, viz: &mut Viz);
+    fn play_any<'a>(&self, input: &'a dyn Any) -> Box<dyn Iterator<Item = Screen> + 'a>;
+}
+
+impl<S: Solver + Sync> DynSolver for S {
+    fn day(&self) -> u32 {
+        S::DAY
+    }
+
+    fn year(&self) -> u16 {
+        S::YEAR
+    }
+
+    fn title(&self) -> &'static str {
+        S::TITLE
+    }
+
+    fn labels(&self, input: &dyn Any) -> [Option<String>; 2] {
+        S::labels(input.downcast_ref().expect("Input of wrong type for labels"))
+    }
+
+    fn example(&self) -> &'static str {
+        S::EXAMPLE
+    }
+
+    fn generate_any(&self, rng: &mut Rng) -> String {
+        S::generate(rng)
+    }
+
+    fn parse_any(&self, input: &str) -> Result<Box<dyn Any>, ParseError> {
+        S::parse(input).map(|i| Box::new(i) as Box<dyn Any>)
+    }
+
+    fn part1_any(&self, input: &dyn Any) -> Box<dyn Display> {
+        Box::new(S::part1(input.downcast_ref().expect("Input of wrong type for part1")))
+    }
+
+    fn part2_any(&self, input: &dyn Any) -> Box<dyn Display> {
+        Box::new(S::part2(input.downcast_ref().expect("Input of wrong type for part2")))
+    }
+
+    fn visualize_any(&self, input: &dyn Any, viz: &mut Viz) {
+        S::visualize(input.downcast_ref().expect("Input of wrong type for visualize"), viz)
+    }
+
+    fn play_any<'a>(&self, input: &'a dyn Any) -> Box<dyn Iterator<Item = Screen> + 'a> {
+        S::play(input.downcast_ref().expect("Input of wrong type for play"))
+    }
+}
+
+// Rng is a small deterministic random number generator (xorshift64*), good enough to generate puzzle inputs
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift stays at zero once it gets there, so that one seed gets a state of its own
+        match seed ^ 0x9E37_79B9_7F4A_7C15 {
+            0 => Rng(0x9E37_79B9_7F4A_7C15),
+            state => Rng(state),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // random number in the range 0..n
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+// what to run: a plain function that reports to the ExRunner itself, or a Solver
+enum Exercise<T> {
+    Func(fn(T, &mut ExRunner)),
+    Solver(&'static dyn DynSolver),
 }
 
 pub struct ExCtx<'a, T: BufRead> {
-    f: fn(T, &mut ExRunner),
+    ex: Exercise<T>,
     input: T,
     debug: Option<Box<dyn Write + 'a>>,
+    trace: Option<Level>,
+    viz: Option<Viz>,
 }
 
 impl<'a, T: BufRead> ExCtx<'a, T> {
     pub fn new(f: fn(T, &mut ExRunner), input: T) -> ExCtx<'a, T> {
-        ExCtx { f, input, debug: None }
+        ExCtx { ex: Exercise::Func(f), input, debug: None, trace: None, viz: None }
+    }
+
+    pub fn from_solver(solver: &'static dyn DynSolver, input: T) -> ExCtx<'a, T> {
+        ExCtx { ex: Exercise::Solver(solver), input, debug: None, trace: None, viz: None }
     }
 
     pub fn with_stderr(&mut self) -> &Self {
@@ -40,19 +197,60 @@ impl<'a, T: BufRead> ExCtx<'a, T> {
         self
     }
 
+    // switch on the info!, debug! and trace! macros up to level. The messages are written to the
+    // debug output at the end of the run, and kept in the ExRunner.
+    pub fn with_trace(&mut self, level: Level) -> &Self {
+        self.trace = Some(level);
+        self
+    }
+
+    // collect at most limit frames while running, see ExRunner::frame
+    pub fn with_viz(&mut self, limit: usize) -> &Self {
+        self.viz = Some(Viz::new(limit));
+        self
+    }
+
     pub fn do_run<'b>(mut self, name: String) -> ExRunner<'b, 'a>
     {
         let mut r = ExRunner::new(name);
         r.debug = self.debug.take();
-        (self.f)(self.input, &mut r);
+        r.viz = self.viz.take();
+        trace::start(self.trace);
+        match self.ex {
+            Exercise::Func(f) => f(self.input, &mut r),
+            Exercise::Solver(s) => run_solver(s, self.input, &mut r),
+        };
         r.totaltime = Some(r.start.elapsed());
+        r.log = trace::finish();
         if let Some(mut h) = r.debug.take() {
+            for rec in &r.log {
+                writeln!(h, "{} {rec}", r.name).expect("Cannot write to debug");
+            }
             h.flush().expect("Cannot flush debug");
         }
         r
     }
 }
 
+// run a solver on an input, reporting to the ExRunner like a plain function does
+pub fn run_solver(s: &dyn DynSolver, input: impl BufRead, r: &mut ExRunner) {
+    let input = io::read_to_string(input).expect("Error reading input");
+    let parsed = match s.parse_any(&input) {
+        Ok(p) => p,
+        Err(e) => {
+            r.error = Some(e);
+            return;
+        },
+    };
+    r.parse_done();
+    let [label1, label2] = s.labels(parsed.as_ref());
+    r.part1(s.part1_any(parsed.as_ref()), label1.as_deref());
+    r.part2(s.part2_any(parsed.as_ref()), label2.as_deref());
+    if let Some(viz) = r.viz.as_mut() {
+        s.visualize_any(parsed.as_ref(), viz);
+    }
+}
+
 impl<'a, 'b> ExRunner<'a, 'b> {
     pub fn new(name: String) -> ExRunner<'static, 'static> {
         ExRunner { name, ..Default::default() }
@@ -64,6 +262,12 @@ impl<'a, 'b> ExRunner<'a, 'b> {
         ct.do_run(name)
     }
 
+    pub fn run_solver<T: BufRead>(name: String, solver: &'static dyn DynSolver, input: T) -> ExRunner<'a, 'b> {
+        let mut ct = ExCtx::from_solver(solver, input);
+        ct.with_stdout();
+        ct.do_run(name)
+    }
+
     pub fn name(&self) -> &String {
         &self.name
     }
@@ -82,6 +286,7 @@ impl<'a, 'b> ExRunner<'a, 'b> {
             _ => 1,
         };
         self.runtime[i] = Some(elapsed);
+        trace::set_phase(if part == 0 { Phase::Part2 } else { Phase::Cleanup });
     }
 
     pub fn part1<T>(&mut self, answ: T, label: Option<&str>)
@@ -101,6 +306,7 @@ impl<'a, 'b> ExRunner<'a, 'b> {
             None => self.parsetime.insert(self.start.elapsed()),
             Some(_) => panic!("Parsing done twice??"),
         };
+        trace::set_phase(Phase::Part1);
     }
 
     pub fn debugln(&mut self, msg: &str) {
@@ -109,10 +315,31 @@ impl<'a, 'b> ExRunner<'a, 'b> {
         }
     }
 
+    // the messages of the info!, debug! and trace! macros during the run
+    pub fn log(&self) -> &[Record] {
+        &self.log
+    }
+
     pub fn answ(&self) -> Vec<Option<String>> {
         self.answ.iter().map(|b| b.as_ref().map(|x| x.to_string())).collect()
     }
 
+    // the parse error, if the input could not be parsed
+    pub fn error(&self) -> Option<&ParseError> {
+        self.error.as_ref()
+    }
+
+    // add a frame when visualising. The closure is only called when frames are collected.
+    pub fn frame(&mut self, draw: impl FnOnce() -> Frame) {
+        if let Some(viz) = self.viz.as_mut() {
+            viz.frame(draw);
+        }
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        self.viz.as_ref().map(|v| v.frames()).unwrap_or(&[])
+    }
+
     pub fn parsetime(&self) -> Option<Duration> {
         self.parsetime
     }
@@ -137,34 +364,42 @@ impl<'a, 'b> ExRunner<'a, 'b> {
     }
 
     pub fn print_raw(&self) {
-        if self.runtime[0].is_none() {
-            println!("{} did not produce any answers", self.name);
+        print!("{}", self.raw());
+    }
+
+    // the answers and timings, as print_raw shows them
+    pub fn raw(&self) -> String {
+        let mut out = String::new();
+        if let Some(e) = &self.error {
+            out += &format!("{} failed to parse input: {e}\n", self.name);
+        } else if self.runtime[0].is_none() {
+            out += &format!("{} did not produce any answers\n", self.name);
         } else {
-            println!("{}:", self.name);
-            let answers = self.answ();
-            for i in 0..=1 {
-                if let Some(a) = &answers[i] {
-                    let sep = if a.contains("\n") { "\n" } else { " " };
-                    println!("{}:{}{}", self.label[i], sep, a);
+            out += &format!("{}:\n", self.name);
+            for (label, answer) in self.label.iter().zip(self.answ()) {
+                if let Some(a) = answer {
+                    let sep = if a.contains('\n') { "\n" } else { " " };
+                    out += &format!("{}:{}{}\n", label, sep, a);
                 }
             }
             if let Some(pt) = self.parsetime {
-                println!("Parsing took: {}", duration_format(&pt))
+                out += &format!("Parsing took: {}\n", duration_format(&pt));
             }
-            let ordinals = vec!["first", "second"];
-            let runtimes = vec![self.time1(), self.time2()];
-            for i in 0..=1 {
-                if let Some(rt) = runtimes[i] {
-                    println!("Calculating {} answer took: {}", ordinals[i], duration_format(&rt));
+            let ordinals = ["first", "second"];
+            let runtimes = [self.time1(), self.time2()];
+            for (ordinal, runtime) in ordinals.iter().zip(runtimes) {
+                if let Some(rt) = runtime {
+                    out += &format!("Calculating {} answer took: {}\n", ordinal, duration_format(&rt));
                 }
             }
         }
         if let Some(ct) = self.cleanuptime() {
-            println!("Cleanup took: {}", duration_format(&ct));
+            out += &format!("Cleanup took: {}\n", duration_format(&ct));
         }
         if let Some(tt) = self.totaltime() {
-            println!("Total exercise time: {}", duration_format(&tt));
+            out += &format!("Total exercise time: {}\n", duration_format(&tt));
         }
+        out
     }
 }
 
@@ -178,11 +413,90 @@ impl<'a, 'b> Default for ExRunner<'a, 'b> {
             parsetime: None,
             runtime: [None; 2],
             totaltime: None,
+            error: None,
             debug: None,
+            log: Vec::new(),
+            viz: None,
+        }
+    }
+}
+
+// ParseError is returned by the puzzle parsers when the input is not what they expect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    msg: String,
+    line: Option<usize>,
+    col: Option<usize>,
+    // the offending part of the input, and the file it came from
+    text: Option<String>,
+    file: Option<String>,
+}
+
+impl ParseError {
+    pub fn new(msg: impl Into<String>) -> ParseError {
+        ParseError { msg: msg.into(), line: None, col: None, text: None, file: None }
+    }
+
+    // attach the (1-based) line number the error occurred on
+    pub fn at_line(mut self, line: usize) -> ParseError {
+        self.line = Some(line);
+        self
+    }
+
+    // attach the (1-based) column, counted in characters
+    pub fn at_col(mut self, col: usize) -> ParseError {
+        self.col = Some(col);
+        self
+    }
+
+    // attach the text that could not be parsed
+    pub fn with_text(mut self, text: impl Into<String>) -> ParseError {
+        self.text = Some(text.into());
+        self
+    }
+
+    // attach the name of the input file
+    pub fn in_file(mut self, file: impl Into<String>) -> ParseError {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn col(&self) -> Option<usize> {
+        self.col
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut location: Vec<String> = self.file.iter().cloned().collect();
+        location.extend(self.line.map(|l| format!("line {l}")));
+        location.extend(self.col.filter(|_| self.line.is_some()).map(|c| format!("column {c}")));
+        if location.is_empty() {
+            write!(f, "{}", self.msg)
+        } else {
+            write!(f, "{}: {}", location.join(", "), self.msg)
         }
     }
 }
 
+impl Error for ParseError {}
+
 // Format a duration with 3 digits precision only... unless it's over 1000 seconds then all seconds are shown.
 pub fn duration_format(d: &Duration) -> String {
     let mut nanos = d.as_nanos();
@@ -309,6 +623,117 @@ mod tests {
         assert_eq!(debugout, b"debug via run\n");
     }
 
+    struct Summer;
+
+    impl Solver for Summer {
+        type Input = Vec<i32>;
+        type Answer1 = i32;
+        type Answer2 = usize;
+        const DAY: u32 = 0;
+        const YEAR: u16 = 2021;
+        const TITLE: &'static str = "Summer";
+        const LABEL2: Option<&'static str> = Some("count");
+        const EXAMPLE: &'static str = "1\n2\n3\n";
+
+        fn parse(input: &str) -> Result<Self::Input, ParseError> {
+            input.lines().enumerate()
+                .map(|(i, l)| l.parse().map_err(|_| ParseError::new("Not a number").at_line(i + 1)))
+                .collect()
+        }
+
+        fn part1(input: &Self::Input) -> i32 {
+            crate::debug!("summing {} numbers", input.len());
+            input.iter().sum()
+        }
+
+        fn part2(input: &Self::Input) -> usize {
+            crate::trace!("counting {input:?}");
+            input.len()
+        }
+
+        // a frame per number, as a bar of that length
+        fn visualize(input: &Self::Input, viz: &mut Viz) {
+            for n in input {
+                viz.frame(|| Grid::new(*n as usize, 1, Rgb::WHITE));
+            }
+        }
+    }
+
+    #[test]
+    fn solver_run() {
+        let input = BufReader::new(Summer.example().as_bytes());
+        let run = ExRunner::run_solver("solver_run".to_string(), &Summer, input);
+        assert_eq!(run.answ(), vec![Some("6".to_string()), Some("3".to_string())]);
+        assert_eq!(run.label, ["part1".to_string(), "count".to_string()]);
+        assert!(run.parsetime().is_some());
+        assert!(run.time1().is_some());
+        assert!(run.time2().is_some());
+        assert!(run.error().is_none());
+        assert_eq!(Summer.title(), "Summer");
+        assert!(run.frames().is_empty());
+    }
+
+    #[test]
+    fn solver_viz() {
+        let mut ctx = ExCtx::from_solver(&Summer, BufReader::new(Summer.example().as_bytes()));
+        ctx.with_viz(2);
+        let run = ctx.do_run("solver_viz".to_string());
+        assert_eq!(run.frames().iter().map(|f| f.width()).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn solver_trace() {
+        let mut debugout: Vec<u8> = Vec::new();
+        let mut ctx = ExCtx::from_solver(&Summer, BufReader::new(Summer.example().as_bytes()));
+        {
+            ctx.with_debugwrite(&mut debugout);
+            ctx.with_trace(Level::Debug);
+            let run = ctx.do_run("solver_trace".to_string());
+            assert_eq!(run.log(), [Record { level: Level::Debug, phase: Phase::Part1, msg: "summing 3 numbers".to_string() }]);
+        }
+        assert_eq!(debugout, b"solver_trace [part1 debug] summing 3 numbers\n");
+        let run = ExRunner::run_solver("solver_trace".to_string(), &Summer, BufReader::new(Summer.example().as_bytes()));
+        assert!(run.log().is_empty());
+    }
+
+    #[test]
+    fn solver_parse_error() {
+        let input = BufReader::new("1\nx\n".as_bytes());
+        let run = ExRunner::run_solver("solver_parse_error".to_string(), &Summer, input);
+        assert_eq!(run.error(), Some(&ParseError::new("Not a number").at_line(2)));
+        assert_eq!(run.answ(), vec![None, None]);
+        assert!(run.parsetime().is_none());
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut r1 = Rng::new(42);
+        let mut r2 = Rng::new(42);
+        let v1: Vec<u64> = (0..10).map(|_| r1.below(100)).collect();
+        let v2: Vec<u64> = (0..10).map(|_| r2.below(100)).collect();
+        assert_eq!(v1, v2);
+        assert!(v1.iter().all(|v| *v < 100));
+        assert_ne!(Rng::new(0).next_u64(), 0);
+        let mut r = Rng::new(0x9E37_79B9_7F4A_7C15);
+        assert!((0..10).any(|_| r.next_u64() != 0));
+        assert_eq!(Summer.generate_any(&mut r1), "1\n2\n3\n");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let e = ParseError::new("Input should be numbers");
+        assert_eq!(e.to_string(), "Input should be numbers");
+        let e = e.at_line(3);
+        assert_eq!(e.line(), Some(3));
+        assert_eq!(e.to_string(), "line 3: Input should be numbers");
+        let e = e.at_col(12);
+        assert_eq!(e.col(), Some(12));
+        assert_eq!(e.to_string(), "line 3, column 12: Input should be numbers");
+        let e = e.with_text("x").in_file("input.txt");
+        assert_eq!((e.text(), e.file()), (Some("x"), Some("input.txt")));
+        assert_eq!(e.to_string(), "input.txt, line 3, column 12: Input should be numbers");
+    }
+
     #[test]
     fn test_d_format() {
         assert_eq!(duration_format(&Duration::new(0, 10)), "10ns");
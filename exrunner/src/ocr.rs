@@ -0,0 +1,225 @@
+use crate::Grid;
+
+// A font of capital letters as drawn by puzzles that show their answer on a screen. Letters
+// are `width` by `height` pixels, with `spacing` empty columns between them.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Font {
+    pub width: usize,
+    pub height: usize,
+    pub spacing: usize,
+    glyphs: &'static [(char, &'static str)],
+}
+
+// the 4 by 6 font, as in 2016 day 8, 2019 day 8 and 11, 2021 day 13 and 2022 day 10
+pub const SMALL: Font = Font { width: 4, height: 6, spacing: 1, glyphs: &[
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Y', "#...#....#.#..#...#...#."),
+    ('Z', "####...#..#..#..#...####"),
+] };
+
+// the 6 by 10 font, as in 2018 day 10
+pub const LARGE: Font = Font { width: 6, height: 10, spacing: 2, glyphs: &[
+    ('A', "..##...#..#.#....##....##....########....##....##....##....#"),
+    ('B', "#####.#....##....##....######.#....##....##....##....######."),
+    ('C', ".####.#....##.....#.....#.....#.....#.....#.....#....#.####."),
+    ('E', "#######.....#.....#.....#####.#.....#.....#.....#.....######"),
+    ('F', "#######.....#.....#.....#####.#.....#.....#.....#.....#....."),
+    ('G', ".####.#....##.....#.....#.....#..####....##....##...##.###.#"),
+    ('H', "#....##....##....##....########....##....##....##....##....#"),
+    ('J', "...###....#.....#.....#.....#.....#.....#.#...#.#...#..###.."),
+    ('K', "#....##...#.#..#..#.#...##....##....#.#...#..#..#...#.#....#"),
+    ('L', "#.....#.....#.....#.....#.....#.....#.....#.....#.....######"),
+    ('N', "#....###...###...##.#..##.#..##..#.##..#.##...###...###....#"),
+    ('P', "#####.#....##....##....######.#.....#.....#.....#.....#....."),
+    ('R', "#####.#....##....##....######.#..#..#...#.#...#.#....##....#"),
+    ('X', "#....##....#.#..#..#..#...##....##...#..#..#..#.#....##....#"),
+    ('Z', "######.....#.....#....#....#....#....#....#.....#.....######"),
+] };
+
+pub const FONTS: [&Font; 2] = [&SMALL, &LARGE];
+
+// glyphs that match less than this are not recognised
+pub const MIN_CONFIDENCE: f64 = 0.85;
+
+// the letter a part of the screen looks most like
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    pub best: char,
+    // the part of the pixels that match the letter, 1.0 for a perfect match
+    pub confidence: f64,
+}
+
+impl Glyph {
+    // the letter, if it matches well enough
+    pub fn letter(&self) -> Option<char> {
+        (self.confidence >= MIN_CONFIDENCE).then_some(self.best)
+    }
+}
+
+// the letters read from a screen, in the font that matched best
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub font: &'static Font,
+    pub glyphs: Vec<Glyph>,
+}
+
+impl Reading {
+    // the letters, with a ? for glyphs that are not recognised
+    pub fn text(&self) -> String {
+        self.glyphs.iter().map(|g| g.letter().unwrap_or('?')).collect()
+    }
+
+    // whether every glyph was recognised
+    pub fn is_complete(&self) -> bool {
+        self.glyphs.iter().all(|g| g.letter().is_some())
+    }
+
+    // the confidence of the least certain glyph
+    pub fn confidence(&self) -> f64 {
+        self.glyphs.iter().map(|g| g.confidence).fold(1.0, f64::min)
+    }
+}
+
+impl Font {
+    // the best matching letter for the glyph at x, y. Pixels outside the screen are off.
+    fn glyph(&self, screen: &Grid<bool>, x: isize, y: isize) -> Glyph {
+        let pixel = |gx: usize, gy: usize| {
+            let (sx, sy) = (x + gx as isize, y + gy as isize);
+            sx >= 0 && sy >= 0 && *screen.get(sx as usize, sy as usize).unwrap_or(&false)
+        };
+        let cells = self.width * self.height;
+        self.glyphs.iter().map(|(letter, pixels)| {
+            let matching = pixels.chars().enumerate().filter(|(i, c)| (*c == '#') == pixel(i % self.width, i / self.width)).count();
+            Glyph { best: *letter, confidence: matching as f64 / cells as f64 }
+        })
+        .fold(Glyph { best: '?', confidence: 0.0 }, |best, g| if g.confidence > best.confidence { g } else { best })
+    }
+
+    // Read the letters of the font in the lit area of a screen, trying every way the letters
+    // can line up with it. None when the lit area is higher than the letters.
+    fn read(&'static self, screen: &Grid<bool>, lit: (usize, usize, usize, usize)) -> Option<Reading> {
+        let (minx, miny, maxx, maxy) = lit;
+        if maxy - miny + 1 > self.height {
+            return None;
+        }
+        let pitch = self.width + self.spacing;
+        let mut best: Option<(f64, Reading)> = None;
+        // letters can have empty columns and rows at their edges, so the lit area can start inside one
+        for dy in 0..=self.height - (maxy - miny + 1) {
+            for dx in 0..self.width {
+                let (x0, y0) = (minx as isize - dx as isize, miny as isize - dy as isize);
+                let count = (maxx as isize - x0) as usize / pitch + 1;
+                let glyphs: Vec<Glyph> = (0..count).map(|i| self.glyph(screen, x0 + (i * pitch) as isize, y0)).collect();
+                let score = glyphs.iter().map(|g| g.confidence).sum::<f64>() / count as f64;
+                if best.as_ref().is_none_or(|b| score > b.0) {
+                    best = Some((score, Reading { font: self, glyphs }));
+                }
+            }
+        }
+        best.map(|b| b.1)
+    }
+}
+
+// Read the letters on a screen, in whichever font fits best. Empty rows and columns around the
+// letters are ignored. None when nothing is lit, or the letters are too high for any font.
+pub fn read(screen: &Grid<bool>) -> Option<Reading> {
+    let lit: Vec<(usize, usize)> = screen.iter().filter(|(_, on)| **on).map(|(p, _)| p).collect();
+    let minx = lit.iter().map(|p| p.0).min()?;
+    let area = (minx, lit.iter().map(|p| p.1).min()?, lit.iter().map(|p| p.0).max()?, lit.iter().map(|p| p.1).max()?);
+    FONTS.iter().filter_map(|f| f.read(screen, area))
+        .map(|r| (r.glyphs.iter().map(|g| g.confidence).sum::<f64>() / r.glyphs.len() as f64, r))
+        .fold(None, |best: Option<(f64, Reading)>, r| match best {
+            Some(b) if b.0 >= r.0 => Some(b),
+            _ => Some(r),
+        })
+        .map(|b| b.1)
+}
+
+// Read the letters in a drawing made of text. Any two characters can be used for the pixels:
+// characters like . and space are off, and otherwise the most common character is.
+pub fn read_text(drawing: &str) -> Option<Reading> {
+    let mut counts: Vec<(char, usize)> = Vec::new();
+    for c in drawing.chars().filter(|c| *c != '\n' && *c != '\r') {
+        match counts.iter_mut().find(|(k, _)| *k == c) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((c, 1)),
+        }
+    }
+    let off = counts.iter().find(|(c, _)| matches!(c, '.' | ' ' | '_' | '-' | '0'))
+        .or_else(|| counts.iter().max_by_key(|(_, n)| *n))
+        .map(|(c, _)| *c)?;
+    let lines: Vec<&str> = drawing.lines().collect();
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let mut screen = Grid::new(width, lines.len(), false);
+    for (y, l) in lines.iter().enumerate() {
+        for (x, c) in l.chars().enumerate() {
+            screen[(x, y)] = c != off && c != '\r';
+        }
+    }
+    read(&screen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // draw letters of a font next to each other, with the given padding around them
+    fn fixture(font: &Font, letters: &str, pad: usize, on: char, off: char) -> String {
+        let glyph = |c: char| font.glyphs.iter().find(|g| g.0 == c).unwrap().1;
+        let width = pad * 2 + letters.len() * (font.width + font.spacing) - font.spacing;
+        let mut rows = vec![off.to_string().repeat(width); pad];
+        for y in 0..font.height {
+            let parts: Vec<String> = letters.chars().map(|c| glyph(c)[y * font.width..(y + 1) * font.width].to_string()).collect();
+            let row = off.to_string().repeat(pad) + &parts.join(&".".repeat(font.spacing)) + &off.to_string().repeat(pad);
+            rows.push(row.replace('#', &on.to_string()).replace('.', &off.to_string()));
+        }
+        rows.extend(vec![off.to_string().repeat(width); pad]);
+        rows.join("\n")
+    }
+
+    #[test]
+    fn test_fonts() {
+        for font in FONTS {
+            assert!(font.glyphs.iter().all(|g| g.1.len() == font.width * font.height), "{} wide", font.width);
+            let letters: String = font.glyphs.iter().map(|g| g.0).collect();
+            let reading = read_text(&fixture(font, &letters, 0, '#', '.')).unwrap();
+            assert_eq!(reading.text(), letters);
+            assert_eq!(reading.font, font);
+            assert_eq!(reading.confidence(), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_read() {
+        // padding and other characters
+        assert_eq!(read_text(&fixture(&SMALL, "IJLY", 3, '█', ' ')).unwrap().text(), "IJLY");
+        assert_eq!(read_text(&fixture(&LARGE, "NXZ", 2, 'o', 'x')).unwrap().text(), "NXZ");
+        // a pixel off still reads, with less confidence
+        let mut noisy: Vec<char> = fixture(&SMALL, "HELLO", 0, '#', '.').chars().collect();
+        noisy[1] = '#';
+        let reading = read_text(&noisy.iter().collect::<String>()).unwrap();
+        assert_eq!(reading.text(), "HELLO");
+        assert!(reading.glyphs[0].confidence < 1.0 && reading.glyphs[1].confidence == 1.0);
+        // a block is no letter
+        let block = fixture(&SMALL, "A", 0, '#', '.').replace('.', "#");
+        let reading = read_text(&(block + "\n....")).unwrap();
+        assert_eq!(reading.glyphs[0].letter(), None);
+        assert!(!reading.is_complete());
+        assert_eq!(read_text("...\n..."), None);
+    }
+}
@@ -0,0 +1,185 @@
+use std::str::FromStr;
+use crate::{Grid, ParseError};
+
+// Text is a piece of the puzzle input that remembers where it was found, so errors can point
+// at the exact line and column. Splitting a Text gives Texts again. Lines and columns are 1-based,
+// columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Text<'a> {
+    text: &'a str,
+    line: usize,
+    col: usize,
+}
+
+// the position after the given text, when it starts at (line, col)
+fn advance((line, col): (usize, usize), text: &str) -> (usize, usize) {
+    match text.rfind('\n') {
+        Some(nl) => (line + text.matches('\n').count(), text[nl + 1..].chars().count() + 1),
+        None => (line, col + text.chars().count()),
+    }
+}
+
+impl<'a> Text<'a> {
+    // the whole input, starting at line 1, column 1
+    pub fn new(text: &'a str) -> Text<'a> {
+        Text { text, line: 1, col: 1 }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    // an error pointing at this text. Only the first line of the text is kept in the error.
+    pub fn err(&self, msg: impl Into<String>) -> ParseError {
+        let text = self.text.lines().next().unwrap_or("");
+        ParseError::new(msg).at_line(self.line).at_col(self.col).with_text(text)
+    }
+
+    // the position of part, which should be a slice of this text
+    fn sub(&self, part: &'a str) -> Text<'a> {
+        let offset = part.as_ptr() as usize - self.text.as_ptr() as usize;
+        let (line, col) = advance((self.line, self.col), &self.text[..offset]);
+        Text { text: part, line, col }
+    }
+
+    // the rest of the text, starting at a byte offset
+    pub fn from(&self, offset: usize) -> Text<'a> {
+        self.sub(&self.text[offset..])
+    }
+
+    pub fn trim(&self) -> Text<'a> {
+        self.sub(self.text.trim())
+    }
+
+    pub fn strip_prefix(&self, prefix: &str) -> Option<Text<'a>> {
+        self.text.strip_prefix(prefix).map(|rest| self.sub(rest))
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = Text<'a>> + 'a {
+        let (line, col) = (self.line, self.col);
+        self.text.lines().enumerate().map(move |(i, l)| Text { text: l, line: line + i, col: if i == 0 { col } else { 1 } })
+    }
+
+    // the parts of the text separated by blank lines, like the boards in day 4
+    pub fn sections(&self) -> Vec<Text<'a>> {
+        let mut sections = Vec::new();
+        // the first and last line of the current section
+        let mut current: Option<(Text<'a>, Text<'a>)> = None;
+        for l in self.lines().chain([Text { text: "", line: 0, col: 0 }]) {
+            if !l.text.trim().is_empty() {
+                current = Some((current.map_or(l, |(first, _)| first), l));
+            } else if let Some((first, last)) = current.take() {
+                let start = first.text.as_ptr() as usize - self.text.as_ptr() as usize;
+                let end = last.text.as_ptr() as usize - self.text.as_ptr() as usize + last.text.len();
+                sections.push(Text { text: &self.text[start..end], ..first });
+            }
+        }
+        sections
+    }
+
+    // split on a separator. Keeps track of the position while walking the text, so splitting
+    // long lists stays cheap.
+    pub fn split(&self, sep: &'a str) -> impl Iterator<Item = Text<'a>> + 'a {
+        let whole = *self;
+        let mut pos = (whole.line, whole.col);
+        let mut done = 0;
+        whole.text.split(sep).map(move |part| {
+            let offset = part.as_ptr() as usize - whole.text.as_ptr() as usize;
+            pos = advance(pos, &whole.text[done..offset]);
+            done = offset;
+            Text { text: part, line: pos.0, col: pos.1 }
+        })
+    }
+
+    pub fn split_whitespace(&self) -> impl Iterator<Item = Text<'a>> + 'a {
+        let whole = *self;
+        let mut pos = (whole.line, whole.col);
+        let mut done = 0;
+        whole.text.split_whitespace().map(move |part| {
+            let offset = part.as_ptr() as usize - whole.text.as_ptr() as usize;
+            pos = advance(pos, &whole.text[done..offset]);
+            done = offset;
+            Text { text: part, line: pos.0, col: pos.1 }
+        })
+    }
+
+    // split into the (trimmed) parts before and after the first separator, like "a -> b" rules
+    pub fn pair(&self, sep: &str) -> Result<(Text<'a>, Text<'a>), ParseError> {
+        let (a, b) = self.text.split_once(sep).ok_or_else(|| self.err(format!("Expected '{sep}'")))?;
+        Ok((self.sub(a).trim(), self.sub(b).trim()))
+    }
+
+    // split a "key value" line on the first whitespace
+    pub fn key_value(&self) -> Result<(Text<'a>, Text<'a>), ParseError> {
+        let text = self.trim();
+        let sp = text.text.find(char::is_whitespace).ok_or_else(|| text.err("Expected a key and a value"))?;
+        Ok((text.sub(&text.text[..sp]), text.from(sp).trim()))
+    }
+
+    // parse the (trimmed) text, msg is the error if that fails
+    pub fn parse<T: FromStr>(&self, msg: &str) -> Result<T, ParseError> {
+        let text = self.trim();
+        text.text.parse().map_err(|_| text.err(msg))
+    }
+
+    // a list of numbers (or anything else that parses), like "3,4,3,1,2"
+    pub fn numbers<T: FromStr>(&self, sep: &'a str, msg: &str) -> Result<Vec<T>, ParseError> {
+        self.trim().split(sep).map(|t| t.parse(msg)).collect()
+    }
+
+    // a grid with a cell for every character. cell returns None for characters that are not valid,
+    // msg is the error for those.
+    pub fn grid<T>(&self, cell: impl Fn(char) -> Option<T>, msg: &str) -> Result<Grid<T>, ParseError> {
+        let mut width = None;
+        let mut rows = Vec::new();
+        for l in self.lines() {
+            let row = l.text.chars().enumerate()
+                .map(|(i, c)| cell(c).ok_or_else(|| ParseError::new(msg).at_line(l.line).at_col(l.col + i).with_text(c)))
+                .collect::<Result<Vec<T>, ParseError>>()?;
+            let w = *width.get_or_insert(row.len());
+            if row.len() != w {
+                return Err(ParseError::new("Grid rows should all be of the same length").at_line(l.line).at_col(l.col + row.len().min(w)));
+            }
+            rows.push(row);
+        }
+        Grid::from_rows(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let t = Text::new("12, 7,x\n\nab -> c\n");
+        let first = t.lines().next().unwrap();
+        assert_eq!(first.numbers::<u32>(",", "Expected number"), Err(ParseError::new("Expected number").at_line(1).at_col(7).with_text("x")));
+        let rule = t.lines().nth(2).unwrap().pair("->").unwrap();
+        assert_eq!((rule.1.as_str(), rule.1.line(), rule.1.col()), ("c", 3, 7));
+        assert_eq!(t.sections().iter().map(|s| (s.as_str(), s.line())).collect::<Vec<_>>(), vec![("12, 7,x", 1), ("ab -> c", 3)]);
+        let kv = Text::new("  forward 5").key_value().unwrap();
+        assert_eq!((kv.0.as_str(), kv.1.as_str(), kv.1.col()), ("forward", "5", 11));
+    }
+
+    #[test]
+    fn test_grid() {
+        let t = Text::new("#.\n.#\n");
+        let g = t.grid(|c| Some(c == '#'), "").unwrap();
+        assert_eq!(g.render(|b| if *b { '#' } else { '.' }), "#.\n.#");
+        assert_eq!(Text::new("#.\n.#x\n").grid(|c| Some(c == '#'), ""),
+            Err(ParseError::new("Grid rows should all be of the same length").at_line(2).at_col(3)));
+    }
+}
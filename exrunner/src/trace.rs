@@ -0,0 +1,155 @@
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Display};
+
+// Levelled debug output for solvers, through the info!, debug! and trace! macros. Tracing is
+// switched on per run (see ExCtx::with_trace), and messages above the level of the run are never
+// formatted, so trace calls can stay in a solver without slowing it down.
+// Every message remembers the phase of the run it was written in.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    // the level for a number of -v flags: none for 0, info for 1, and so on
+    pub fn from_verbosity(v: u8) -> Option<Level> {
+        match v {
+            0 => None,
+            1 => Some(Level::Info),
+            2 => Some(Level::Debug),
+            _ => Some(Level::Trace),
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        };
+        f.write_str(s)
+    }
+}
+
+// the phases of a run, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Parse,
+    Part1,
+    Part2,
+    Cleanup,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Phase::Parse => "parse",
+            Phase::Part1 => "part1",
+            Phase::Part2 => "part2",
+            Phase::Cleanup => "cleanup",
+        };
+        f.write_str(s)
+    }
+}
+
+// a traced message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub level: Level,
+    pub phase: Phase,
+    pub msg: String,
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{} {}] {}", self.phase, self.level, self.msg)
+    }
+}
+
+// the state is kept per thread, so puzzles (and tests) running in parallel do not mix up their output
+thread_local! {
+    static LEVEL: Cell<Option<Level>> = const { Cell::new(None) };
+    static PHASE: Cell<Phase> = const { Cell::new(Phase::Parse) };
+    static RECORDS: RefCell<Vec<Record>> = const { RefCell::new(Vec::new()) };
+}
+
+// is output at this level switched on for the current run
+pub fn enabled(level: Level) -> bool {
+    LEVEL.get().is_some_and(|max| level <= max)
+}
+
+// store a message, use the macros instead of calling this directly
+pub fn record(level: Level, args: fmt::Arguments) {
+    let phase = PHASE.get();
+    RECORDS.with_borrow_mut(|r| r.push(Record { level, phase, msg: args.to_string() }));
+}
+
+// start tracing a run on this thread, at most at the given level
+pub(crate) fn start(level: Option<Level>) {
+    LEVEL.set(level);
+    PHASE.set(Phase::Parse);
+    RECORDS.take();
+}
+
+pub(crate) fn set_phase(phase: Phase) {
+    PHASE.set(phase);
+}
+
+// stop tracing, and return the messages of the run
+pub(crate) fn finish() -> Vec<Record> {
+    LEVEL.set(None);
+    RECORDS.take()
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace_at {
+    ($level:expr, $($arg:tt)+) => {
+        if $crate::trace::enabled($level) {
+            $crate::trace::record($level, format_args!($($arg)+));
+        }
+    };
+}
+
+// info!("format", args...): a message shown from -v on
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::__trace_at!($crate::trace::Level::Info, $($arg)+) };
+}
+
+// debug!("format", args...): a message shown from -vv on
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::__trace_at!($crate::trace::Level::Debug, $($arg)+) };
+}
+
+// trace!("format", args...): a message shown with -vvv
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => { $crate::__trace_at!($crate::trace::Level::Trace, $($arg)+) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        // formatting a message with a disabled level should not even evaluate the arguments
+        let noisy = || -> String { panic!("should not be formatted") };
+        start(Some(Level::Debug));
+        crate::info!("one {}", 1);
+        crate::trace!("{}", noisy());
+        set_phase(Phase::Part2);
+        crate::debug!("two");
+        let records = finish();
+        assert_eq!(records.iter().map(|r| r.to_string()).collect::<Vec<_>>(), vec!["[parse info] one 1", "[part2 debug] two"]);
+        crate::info!("not running");
+        assert!(finish().is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::Grid;
+
+// a colour, as red, green and blue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+    pub const RED: Rgb = Rgb(255, 0, 0);
+
+    pub fn gray(level: u8) -> Rgb {
+        Rgb(level, level, level)
+    }
+
+    // a heatmap colour for value out of max: black, through red and yellow, to white
+    pub fn heat(value: usize, max: usize) -> Rgb {
+        let v = (value.min(max) * 765 / max.max(1)) as u32;
+        let channel = |from: u32| (v.saturating_sub(from).min(255)) as u8;
+        Rgb(channel(0), channel(255), channel(510))
+    }
+}
+
+// a frame is a picture of the state of a puzzle, with one colour for every cell
+pub type Frame = Grid<Rgb>;
+
+// Viz collects the frames a solver draws when it is visualised. Frames are only drawn when
+// asked for, and at most `limit` of them are kept.
+#[derive(Debug, Default)]
+pub struct Viz {
+    frames: Vec<Frame>,
+    limit: usize,
+}
+
+impl Viz {
+    pub fn new(limit: usize) -> Viz {
+        Viz { frames: Vec::new(), limit }
+    }
+
+    // add a frame. The closure is not called when the limit is reached.
+    pub fn frame(&mut self, draw: impl FnOnce() -> Frame) {
+        if self.frames.len() < self.limit {
+            self.frames.push(draw());
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.frames.len() >= self.limit
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.frames
+    }
+}
+
+// a cell of a text screen: a character in a colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Rgb,
+}
+
+// Screen is one state of a puzzle drawn as coloured text, for `aoc play`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    pub caption: String,
+    pub cells: Grid<Cell>,
+}
+
+impl Screen {
+    // an empty screen of the given size
+    pub fn new(caption: impl Into<String>, width: usize, height: usize) -> Screen {
+        Screen { caption: caption.into(), cells: Grid::new(width, height, Cell { ch: ' ', fg: Rgb::WHITE }) }
+    }
+
+    // write text starting at x, y. Text that does not fit is cut off.
+    pub fn text(&mut self, x: usize, y: usize, text: &str, fg: Rgb) {
+        for (i, ch) in text.chars().enumerate() {
+            if let Some(cell) = self.cells.get_mut(x + i, y) {
+                *cell = Cell { ch, fg };
+            }
+        }
+    }
+
+    // the text of the screen without colours, one line per row
+    pub fn plain(&self) -> String {
+        self.cells.render(|c| c.ch)
+    }
+}
+
+// a frame as a binary PPM image
+pub fn to_ppm(frame: &Frame) -> Vec<u8> {
+    let mut ppm = format!("P6\n{} {}\n255\n", frame.width(), frame.height()).into_bytes();
+    ppm.extend(frame.values().flat_map(|Rgb(r, g, b)| [*r, *g, *b]));
+    ppm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viz() {
+        let mut viz = Viz::new(2);
+        for i in 0..5 {
+            viz.frame(|| Grid::new(2, 1, Rgb::gray(i)));
+        }
+        assert!(viz.is_full());
+        assert_eq!(viz.frames().len(), 2);
+        assert_eq!(to_ppm(&viz.frames()[1]), b"P6\n2 1\n255\n\x01\x01\x01\x01\x01\x01");
+        assert_eq!([Rgb::heat(0, 9), Rgb::heat(9, 9), Rgb::heat(3, 9)], [Rgb::BLACK, Rgb::WHITE, Rgb(255, 0, 0)]);
+    }
+
+    #[test]
+    fn test_screen() {
+        let mut screen = Screen::new("test", 4, 2);
+        screen.text(1, 0, "abc", Rgb::RED);
+        screen.text(2, 1, "xyz", Rgb::WHITE);
+        assert_eq!(screen.plain(), " abc\n  xy");
+        assert_eq!(screen.cells[(1, 0)], Cell { ch: 'a', fg: Rgb::RED });
+    }
+}
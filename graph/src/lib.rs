@@ -0,0 +1,349 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use exrunner::Grid;
+
+pub type NodeId = usize;
+pub type Cost = u64;
+
+// Graph is a directed, weighted graph with nodes numbered 0..len(). The search algorithms only
+// need the outgoing edges of a node, so they work on any graph that can list those.
+pub trait Graph {
+    fn len(&self) -> usize;
+
+    // the outgoing edges of a node, as (to, cost)
+    fn edges(&self, node: NodeId) -> impl Iterator<Item = (NodeId, Cost)>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn neighbours(&self, node: NodeId) -> impl Iterator<Item = NodeId> {
+        self.edges(node).map(|(to, _)| to)
+    }
+
+    // the cheapest cost from start to every node, None for nodes that cannot be reached
+    fn shortest_costs(&self, start: NodeId) -> Vec<Option<Cost>> {
+        let mut best = vec![None; self.len()];
+        let mut heap = BinaryHeap::from([Reverse((0, start))]);
+        best[start] = Some(0);
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            if best[node].is_some_and(|b| b < cost) {
+                // already found a cheaper way here
+                continue;
+            }
+            for (to, c) in self.edges(node) {
+                let new_cost = cost + c;
+                if best[to].is_none_or(|b| new_cost < b) {
+                    best[to] = Some(new_cost);
+                    heap.push(Reverse((new_cost, to)));
+                }
+            }
+        }
+        best
+    }
+
+    // Dijkstra: the cheapest path from start to goal, with its cost
+    fn dijkstra(&self, start: NodeId, goal: NodeId) -> Option<(Cost, Vec<NodeId>)> {
+        self.astar(start, goal, |_| 0)
+    }
+
+    // A*: the cheapest path from start to goal, with its cost. The heuristic gives an estimate of
+    // the cost from a node to the goal, and should never be higher than the real cost.
+    fn astar(&self, start: NodeId, goal: NodeId, heuristic: impl Fn(NodeId) -> Cost) -> Option<(Cost, Vec<NodeId>)> {
+        let mut best = vec![Cost::MAX; self.len()];
+        // the previous node on the cheapest path, start for nodes not reached yet
+        let mut prev = vec![start; self.len()];
+        // the heap is ordered on the estimated total cost. The cost so far is in best.
+        let mut heap = BinaryHeap::from([Reverse((heuristic(start), start))]);
+        best[start] = 0;
+        while let Some(Reverse((estimate, node))) = heap.pop() {
+            let cost = best[node];
+            if estimate > cost + heuristic(node) {
+                // already found a cheaper way here
+                continue;
+            }
+            if node == goal {
+                let mut path = vec![goal];
+                while *path.last().unwrap() != start {
+                    path.push(prev[*path.last().unwrap()]);
+                }
+                path.reverse();
+                return Some((cost, path));
+            }
+            for (to, c) in self.edges(node) {
+                let new_cost = cost + c;
+                if new_cost < best[to] {
+                    best[to] = new_cost;
+                    prev[to] = node;
+                    heap.push(Reverse((new_cost + heuristic(to), to)));
+                }
+            }
+        }
+        None
+    }
+
+    // breadth first search: the number of steps from start to every node, ignoring costs
+    fn bfs(&self, start: NodeId) -> Vec<Option<usize>> {
+        let mut steps = vec![None; self.len()];
+        let mut queue = VecDeque::from([start]);
+        steps[start] = Some(0);
+        while let Some(node) = queue.pop_front() {
+            let s = steps[node].unwrap();
+            for to in self.neighbours(node) {
+                if steps[to].is_none() {
+                    steps[to] = Some(s + 1);
+                    queue.push_back(to);
+                }
+            }
+        }
+        steps
+    }
+
+    // depth first search: all nodes reachable from start, in the order they are visited
+    fn dfs(&self, start: NodeId) -> Vec<NodeId> {
+        let mut seen = vec![false; self.len()];
+        let mut order = Vec::new();
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            if seen[node] {
+                continue;
+            }
+            seen[node] = true;
+            order.push(node);
+            // push in reverse, so the first neighbour is visited first
+            stack.extend(self.neighbours(node).filter(|n| !seen[*n]).collect::<Vec<_>>().into_iter().rev());
+        }
+        order
+    }
+
+    // Count the paths from start to goal. max_visits(node) is the number of times a node may be
+    // visited, None for no limit, and the start counts as a visit. Nodes with a limit must be
+    // visited at most that often, except that up to `extra` times during a path a node may be
+    // visited once more than its limit. A limit of 0 for the start keeps paths from returning to it.
+    // A path ends at the goal, unless through_goal is set: then it may go on and reach the goal
+    // again, counting as another path. Nodes without a limit should not form a cycle, or there
+    // would be endless paths.
+    fn count_paths(&self, start: NodeId, goal: NodeId, max_visits: impl Fn(NodeId) -> Option<usize>, extra: usize, through_goal: bool) -> usize {
+        let limits: Vec<Option<usize>> = (0..self.len()).map(max_visits).collect();
+        let mut visits = vec![0; self.len()];
+        visits[start] = 1;
+        let target = (goal, through_goal);
+        count_paths_from(self, start, target, &limits, &mut visits, extra, &mut HashMap::new())
+    }
+}
+
+// Count the paths from node to goal, given the visits so far. Only the visits of nodes with a
+// limit are counted, so the count can be remembered for every node, visits and extra left.
+fn count_paths_from<G: Graph + ?Sized>(graph: &G, node: NodeId, target: (NodeId, bool), limits: &[Option<usize>], visits: &mut [usize], extra: usize,
+    memo: &mut HashMap<(NodeId, Vec<usize>, usize), usize>) -> usize
+{
+    let (goal, through_goal) = target;
+    let key = (node, visits.to_vec(), extra);
+    if let Some(count) = memo.get(&key) {
+        return *count;
+    }
+    let mut count = 0;
+    for to in graph.neighbours(node) {
+        let mut extra_left = extra;
+        if let Some(limit) = limits[to] {
+            if visits[to] >= limit {
+                if extra == 0 || visits[to] > limit {
+                    continue;
+                }
+                extra_left -= 1;
+            }
+        }
+        if to == goal {
+            count += 1;
+            if !through_goal {
+                continue;
+            }
+        }
+        let limited = limits[to].is_some();
+        if limited {
+            visits[to] += 1;
+        }
+        count += count_paths_from(graph, to, target, limits, visits, extra_left, memo);
+        if limited {
+            visits[to] -= 1;
+        }
+    }
+    memo.insert(key, count);
+    count
+}
+
+// Adjacency is a graph built from a list of edges. The edges are stored per source node in one
+// flat list.
+#[derive(Debug, Clone)]
+pub struct Adjacency {
+    // edges of node n are edges[offsets[n]..offsets[n+1]]
+    offsets: Vec<usize>,
+    edges: Vec<(NodeId, Cost)>,
+}
+
+impl Adjacency {
+    // build a graph of len nodes from a list of directed (from, to, cost) edges
+    pub fn from_edges(len: usize, edges: impl IntoIterator<Item = (NodeId, NodeId, Cost)>) -> Adjacency {
+        let edges: Vec<(NodeId, NodeId, Cost)> = edges.into_iter().collect();
+        let mut offsets = vec![0; len + 1];
+        for (from, _, _) in &edges {
+            assert!(*from < len, "Edge from node {from} outside of graph");
+            offsets[from + 1] += 1;
+        }
+        for n in 0..len {
+            offsets[n + 1] += offsets[n];
+        }
+        // place every edge in the slot of its source node
+        let mut next = offsets.clone();
+        let mut sorted = vec![(0, 0); edges.len()];
+        for (from, to, cost) in edges {
+            assert!(to < len, "Edge to node {to} outside of graph");
+            sorted[next[from]] = (to, cost);
+            next[from] += 1;
+        }
+        Adjacency { offsets, edges: sorted }
+    }
+
+    // same as from_edges, but every edge can be travelled both ways
+    pub fn from_undirected_edges(len: usize, edges: impl IntoIterator<Item = (NodeId, NodeId, Cost)>) -> Adjacency {
+        Adjacency::from_edges(len, edges.into_iter().flat_map(|(a, b, cost)| [(a, b, cost), (b, a, cost)]))
+    }
+}
+
+impl Graph for Adjacency {
+    fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn edges(&self, node: NodeId) -> impl Iterator<Item = (NodeId, Cost)> {
+        self.edges[self.offsets[node]..self.offsets[node + 1]].iter().copied()
+    }
+}
+
+// GridGraph is a graph with a node for every cell of a grid (see grid_node), and edges between
+// horizontal and vertical neighbours. The edges are not stored, but calculated when needed.
+pub struct GridGraph<'a, T, F> {
+    grid: &'a Grid<T>,
+    cost: F,
+}
+
+impl<'a, T, F: Fn(&T, &T) -> Option<Cost>> GridGraph<'a, T, F> {
+    // cost(from, to) returns the cost of a step, or None if the step is not possible.
+    pub fn new(grid: &'a Grid<T>, cost: F) -> GridGraph<'a, T, F> {
+        GridGraph { grid, cost }
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Option<Cost>> Graph for GridGraph<'_, T, F> {
+    fn len(&self) -> usize {
+        self.grid.len()
+    }
+
+    fn edges(&self, node: NodeId) -> impl Iterator<Item = (NodeId, Cost)> {
+        let (x, y) = (node % self.grid.width(), node / self.grid.width());
+        self.grid.neighbours4(x, y).filter_map(move |(nx, ny)| {
+            (self.cost)(&self.grid[(x, y)], &self.grid[(nx, ny)]).map(|c| (grid_node(self.grid, nx, ny), c))
+        })
+    }
+}
+
+// the node of a grid position in a graph built with GridGraph::new
+pub fn grid_node<T>(grid: &Grid<T>, x: usize, y: usize) -> NodeId {
+    y * grid.width() + x
+}
+
+// Labels hands out node numbers for labels, like names in the puzzle input, so a graph can be
+// built from an edge list of labels.
+#[derive(Debug, Clone)]
+pub struct Labels<L> {
+    ids: HashMap<L, NodeId>,
+    labels: Vec<L>,
+}
+
+impl<L: Hash + Eq + Clone> Labels<L> {
+    pub fn new() -> Labels<L> {
+        Labels { ids: HashMap::new(), labels: Vec::new() }
+    }
+
+    // the node number of a label, adding it if it is new
+    pub fn id(&mut self, label: L) -> NodeId {
+        if let Some(id) = self.ids.get(&label) {
+            return *id;
+        }
+        self.labels.push(label.clone());
+        self.ids.insert(label, self.labels.len() - 1);
+        self.labels.len() - 1
+    }
+
+    pub fn get(&self, label: &L) -> Option<NodeId> {
+        self.ids.get(label).copied()
+    }
+
+    pub fn label(&self, id: NodeId) -> &L {
+        &self.labels[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+impl<L: Hash + Eq + Clone> Default for Labels<L> {
+    fn default() -> Labels<L> {
+        Labels::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0 -> 1 -> 3 costs 2, 0 -> 2 -> 3 costs 5, 4 is unreachable
+    fn test_graph() -> Adjacency {
+        Adjacency::from_edges(5, [(0, 1, 1), (1, 3, 1), (0, 2, 1), (2, 3, 4), (4, 3, 1)])
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let g = test_graph();
+        assert_eq!(g.dijkstra(0, 3), Some((2, vec![0, 1, 3])));
+        assert_eq!(g.astar(0, 3, |n| if n == 3 { 0 } else { 1 }), Some((2, vec![0, 1, 3])));
+        assert_eq!(g.dijkstra(0, 4), None);
+        assert_eq!(g.shortest_costs(0), vec![Some(0), Some(1), Some(1), Some(2), None]);
+    }
+
+    #[test]
+    fn test_search() {
+        let g = test_graph();
+        assert_eq!(g.bfs(0), vec![Some(0), Some(1), Some(1), Some(2), None]);
+        assert_eq!(g.dfs(0), vec![0, 1, 3, 2]);
+    }
+
+    #[test]
+    fn test_grid() {
+        let grid = Grid::parse_digits("19\n11\n").unwrap();
+        let g = GridGraph::new(&grid, |_, to| Some(*to as Cost));
+        let goal = grid_node(&grid, 1, 1);
+        assert_eq!(g.dijkstra(0, goal), Some((2, vec![0, 2, 3])));
+    }
+
+    #[test]
+    fn test_count_paths() {
+        // a square a-b-c-d-a with a diagonal a-c
+        let mut labels = Labels::new();
+        let edges: Vec<_> = [("a", "b"), ("b", "c"), ("c", "d"), ("d", "a"), ("a", "c")].iter()
+            .map(|(from, to)| (labels.id(*from), labels.id(to), 1)).collect();
+        let g = Adjacency::from_undirected_edges(labels.len(), edges);
+        let (a, c) = (labels.get(&"a").unwrap(), labels.get(&"c").unwrap());
+        assert_eq!(g.count_paths(a, c, |_| Some(1), 0, false), 3);
+        assert_eq!(g.count_paths(a, c, |n| (n == a).then_some(0), 0, false), 3);
+        assert_eq!(g.count_paths(a, c, |n| (n != c).then_some(0), 0, false), 1);
+        // one node may be visited twice, and paths go on after c, so a-c-b-c and a-b-c-d-c count too
+        assert_eq!(g.count_paths(a, c, |n| Some(if n == a { 0 } else { 1 }), 1, true), 7);
+    }
+}